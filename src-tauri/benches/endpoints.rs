@@ -0,0 +1,165 @@
+//! Seeds a world several times bigger than the default one, then times the endpoints the frontend
+//! refetches after every week and reports any that are slower than their budget. Timings depend
+//! on the machine, so going over budget is only flagged rather than failed on.
+//!
+//! Run with `cargo bench --bench endpoints`
+
+use std::{
+    future::Future,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use oaken::{
+    ctx::OakenCtx,
+    error::OakenResult,
+    models::{
+        league::{League, LeagueInfo},
+        schedule::Schedule,
+        season::Season,
+        standings::Standing,
+        team::{Team, TeamIdentity, TeamInfo},
+        tier::Tier,
+    },
+    paths::Paths,
+};
+
+const LEAGUES: i32 = 16;
+const TIERS_PER_LEAGUE: i32 = 4;
+const TEAMS_PER_TIER: i32 = 16;
+
+/// Seasons to seed. Every one but the last is played out in full
+const SEASONS: i32 = 3;
+const FIRST_YEAR: i32 = 2023;
+
+/// How many times each endpoint is run. The median is what's compared with the budget
+const RUNS: usize = 15;
+
+fn db_path() -> PathBuf {
+    std::env::temp_dir().join(format!("oaken-bench-{}.sqlite", std::process::id()))
+}
+
+async fn seed(ctx: &OakenCtx) -> OakenResult<()> {
+    for league_no in 1..=LEAGUES {
+        let league = League::create(ctx, format!("League {league_no}"), format!("L{league_no}")).await?;
+
+        for rank in 1..=TIERS_PER_LEAGUE {
+            let tier = Tier::create(ctx, format!("Tier {rank}"), rank, league.id).await?;
+
+            for team_no in 1..=TEAMS_PER_TIER {
+                let name = format!("L{league_no} T{rank} Team {team_no}");
+                let identity = TeamIdentity {
+                    abbr: format!("{league_no}{rank}{team_no}"),
+                    city: format!("City {league_no}-{rank}-{team_no}"),
+                    founded: 1900,
+                    primary_color: "#1e293b".to_owned(),
+                    secondary_color: "#f8fafc".to_owned(),
+                };
+
+                Team::create(ctx, name, &identity, 50, 10_000, tier.id, league.id).await?;
+            }
+        }
+    }
+
+    for year in FIRST_YEAR..FIRST_YEAR + SEASONS {
+        let season = Season::create(ctx, year).await?;
+
+        for league in League::get_all(ctx).await? {
+            for tier in league.get_tiers(ctx).await? {
+                Schedule::create_round_robin(ctx, league.id, tier.id, &season).await?;
+            }
+        }
+    }
+
+    // scores only need to be plausible, so they're filled in wholesale rather than simulated.
+    // the current season is left half played
+    ctx.pool().unwrap().exec(&format!(
+        r#"
+        UPDATE matchups
+        SET home_team_score = ABS(RANDOM()) % 120, away_team_score = ABS(RANDOM()) % 120
+        WHERE schedule_id IN (
+            SELECT id FROM schedules WHERE year < {current}
+        ) OR (
+            wk_no <= {played} AND schedule_id IN (SELECT id FROM schedules WHERE year = {current})
+        );
+    "#,
+        current = FIRST_YEAR + SEASONS - 1,
+        played = TEAMS_PER_TIER - 1,
+    ))?;
+
+    for year in FIRST_YEAR..FIRST_YEAR + SEASONS {
+        Standing::rebuild(ctx, &year).await?;
+    }
+
+    Ok(())
+}
+
+/// Runs `endpoint` [`RUNS`] times and returns how long the median run took
+async fn time<T, F, Fut>(endpoint: F) -> Duration
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = OakenResult<T>>,
+{
+    let mut timings = Vec::with_capacity(RUNS);
+
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        endpoint().await.expect("endpoint failed");
+        timings.push(start.elapsed());
+    }
+
+    timings.sort();
+    timings[RUNS / 2]
+}
+
+#[tokio::main]
+async fn main() {
+    let path = db_path();
+    let _ = std::fs::remove_file(&path);
+
+    let ctx = OakenCtx::new(Paths::new(std::env::temp_dir(), std::env::temp_dir()));
+    ctx.init_pool(&path)
+        .await
+        .expect("failed to open bench database");
+
+    let start = Instant::now();
+    ctx.pool().unwrap()
+        .transaction(seed(&ctx))
+        .await
+        .expect("failed to seed bench world");
+
+    println!(
+        "seeded {} teams over {SEASONS} seasons in {:?}",
+        LEAGUES * TIERS_PER_LEAGUE * TEAMS_PER_TIER,
+        start.elapsed()
+    );
+
+    let year = FIRST_YEAR + SEASONS - 1;
+
+    let results = [
+        (
+            "getTeamInfos",
+            time(|| TeamInfo::get_all(&ctx)).await,
+            Duration::from_millis(50),
+        ),
+        (
+            "getLeagueInfos",
+            time(|| LeagueInfo::get_all(&ctx)).await,
+            Duration::from_millis(10),
+        ),
+        (
+            "getStandings",
+            time(|| Standing::get(&ctx, &year)).await,
+            Duration::from_millis(25),
+        ),
+    ];
+
+    ctx.close_pool().await;
+    let _ = std::fs::remove_file(&path);
+
+    for (endpoint, median, budget) in results {
+        let flag = if median > budget { "  OVER BUDGET" } else { "" };
+
+        println!("{endpoint:<16} median {median:>10.2?}  budget {budget:?}{flag}");
+    }
+}
@@ -0,0 +1,575 @@
+//! Runs the game without a window, for scripting season runs on machines with no GUI.
+//!
+//! By default it picks up the save the desktop app last had open, the same way the app does on
+//! launch. Run `oaken-cli help` for the full set of commands and options.
+
+mod tui;
+
+use std::{fmt::Display, fs, io::Write, path::PathBuf, process::ExitCode};
+
+use itertools::Itertools;
+use oaken::{
+    ctx::OakenCtx,
+    error::{OakenError, OakenResult},
+    handlers::{batch::BatchHandlers, game::GameHandlers, save::SaveHandlers},
+    models::{
+        game::GameState,
+        league::{League, LeagueInfo},
+        matchup::{Matchup, Outcome},
+        season::Season,
+        standings::Standing,
+        team::TeamInfo,
+    },
+    paths::Paths,
+};
+
+const USAGE: &str = "\
+Usage: oaken-cli [OPTIONS] <COMMAND>
+
+Commands:
+  restart                         Throw away the game in use and start a new one
+  advance [--weeks N | --to-end]  Play N weeks (1 by default), or the rest of the season
+  standings [--year YEAR] [--league LEAGUE] [--tier TIER]
+                                  Print the tables for YEAR (the current year by default)
+  schedule --team TEAM [--year YEAR]
+                                  Print a team's fixtures and results for YEAR
+  export PATH                     Bundle the game in use into a save archive at PATH
+  simulate --seasons K [--seed S] [--jobs N] [--out PATH]
+                                  Play K seasons of a freshly generated world in memory, each
+                                  with its own seed, and report how they went as CSV
+  tui                             Browse and play the game in a terminal UI
+  help                            Print this message
+
+Options:
+  --save ID       Use save slot ID rather than the one last used
+  --db PATH       Use the database at PATH rather than a save slot, starting a new game in it if
+                  it's empty
+  --config DIR    Read the default leagues.toml and teams.toml from DIR
+  --data DIR      Look for save slots in DIR
+  --json          Print standings, schedules and simulation reports as JSON
+
+LEAGUE may be a league's name or abbreviation, TIER a tier's name or rank, and TEAM a team's id,
+name or abbreviation.";
+
+#[derive(Debug)]
+enum CliError {
+    Usage(String),
+    Oaken(OakenError),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Usage(msg) => write!(f, "{msg}"),
+            Self::Oaken(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<OakenError> for CliError {
+    fn from(value: OakenError) -> Self {
+        Self::Oaken(value)
+    }
+}
+
+type CliResult<T> = Result<T, CliError>;
+
+fn usage_err<T>(msg: impl Into<String>) -> CliResult<T> {
+    Err(CliError::Usage(msg.into()))
+}
+
+/// Where the game to run against lives
+enum Source {
+    /// Whichever save was last in use, as the app would pick on launch
+    LastSave,
+    Save(i32),
+    Db(PathBuf),
+}
+
+enum Advance {
+    Weeks(u32),
+    /// Every week left in the season, then the rollover into the next one
+    ToEnd,
+}
+
+enum Command {
+    Restart,
+    Advance(Advance),
+    Standings {
+        year: Option<i32>,
+        league: Option<String>,
+        tier: Option<String>,
+    },
+    Schedule {
+        team: String,
+        year: Option<i32>,
+    },
+    Export(PathBuf),
+    Simulate {
+        seasons: u32,
+        seed: Option<u64>,
+        jobs: Option<usize>,
+        out: Option<PathBuf>,
+    },
+    Tui,
+    Help,
+}
+
+struct Args {
+    source: Source,
+    config_dir: Option<PathBuf>,
+    data_dir: Option<PathBuf>,
+    json: bool,
+    command: Command,
+}
+
+/// Pulls `--flag VALUE` out of `args`, wherever it is
+fn take_opt(args: &mut Vec<String>, flag: &str) -> CliResult<Option<String>> {
+    let Some(pos) = args.iter().position(|arg| arg == flag) else {
+        return Ok(None);
+    };
+
+    if pos + 1 >= args.len() {
+        return usage_err(format!("{flag} needs a value"));
+    }
+
+    args.remove(pos);
+    Ok(Some(args.remove(pos)))
+}
+
+/// Pulls `--flag` out of `args`, returning whether it was there
+fn take_switch(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|arg| arg == flag) {
+        Some(pos) => {
+            args.remove(pos);
+            true
+        }
+        None => false,
+    }
+}
+
+fn parse_num<T: std::str::FromStr>(flag: &str, value: Option<String>) -> CliResult<Option<T>> {
+    value
+        .map(|value| match value.parse() {
+            Ok(n) => Ok(n),
+            Err(_) => usage_err(format!("{flag} expects a number, got '{value}'")),
+        })
+        .transpose()
+}
+
+impl Args {
+    fn parse(mut args: Vec<String>) -> CliResult<Self> {
+        let save = parse_num("--save", take_opt(&mut args, "--save")?)?;
+        let db = take_opt(&mut args, "--db")?.map(PathBuf::from);
+        let config_dir = take_opt(&mut args, "--config")?.map(PathBuf::from);
+        let data_dir = take_opt(&mut args, "--data")?.map(PathBuf::from);
+        let json = take_switch(&mut args, "--json");
+
+        let source = match (save, db) {
+            (Some(_), Some(_)) => return usage_err("--save and --db can't be used together"),
+            (Some(save_id), None) => Source::Save(save_id),
+            (None, Some(path)) => Source::Db(path),
+            (None, None) => Source::LastSave,
+        };
+
+        let command = match args.first().map(String::as_str) {
+            Some("restart") => Command::Restart,
+            Some("advance") => {
+                let weeks = parse_num("--weeks", take_opt(&mut args, "--weeks")?)?;
+
+                match (weeks, take_switch(&mut args, "--to-end")) {
+                    (Some(_), true) => {
+                        return usage_err("--weeks and --to-end can't be used together")
+                    }
+                    (weeks, false) => Command::Advance(Advance::Weeks(weeks.unwrap_or(1))),
+                    (None, true) => Command::Advance(Advance::ToEnd),
+                }
+            }
+            Some("standings") => Command::Standings {
+                year: parse_num("--year", take_opt(&mut args, "--year")?)?,
+                league: take_opt(&mut args, "--league")?,
+                tier: take_opt(&mut args, "--tier")?,
+            },
+            Some("schedule") => Command::Schedule {
+                year: parse_num("--year", take_opt(&mut args, "--year")?)?,
+                team: match take_opt(&mut args, "--team")? {
+                    Some(team) => team,
+                    None => return usage_err("schedule needs a --team"),
+                },
+            },
+            Some("export") if args.len() == 2 => Command::Export(PathBuf::from(args.remove(1))),
+            Some("export") => return usage_err("export needs a PATH to write the archive to"),
+            Some("simulate") if !matches!(source, Source::LastSave) => {
+                return usage_err("simulate plays in memory, so --save and --db don't apply")
+            }
+            Some("simulate") => Command::Simulate {
+                seasons: match parse_num("--seasons", take_opt(&mut args, "--seasons")?)? {
+                    Some(seasons) => seasons,
+                    None => return usage_err("simulate needs a number of --seasons"),
+                },
+                seed: parse_num("--seed", take_opt(&mut args, "--seed")?)?,
+                jobs: parse_num("--jobs", take_opt(&mut args, "--jobs")?)?,
+                out: take_opt(&mut args, "--out")?.map(PathBuf::from),
+            },
+            Some("tui") => Command::Tui,
+            Some("help" | "--help" | "-h") => Command::Help,
+            Some(other) => return usage_err(format!("unknown command '{other}'")),
+            None => return usage_err("no command given"),
+        };
+
+        if let Some(extra) = args.get(1) {
+            return usage_err(format!("unexpected argument '{extra}'"));
+        }
+
+        Ok(Self {
+            source,
+            config_dir,
+            data_dir,
+            json,
+            command,
+        })
+    }
+}
+
+/// Works out where configs and saves live from `args`, falling back on the app's own dirs
+fn paths(args: &Args) -> CliResult<Paths> {
+    let app_paths = Paths::for_app();
+
+    let (Some(config_dir), Some(data_dir)) = (
+        args.config_dir
+            .clone()
+            .or_else(|| Some(app_paths.as_ref()?.get_default_config_dir())),
+        args.data_dir
+            .clone()
+            .or_else(|| Some(app_paths.as_ref()?.get_local_data_dir())),
+    ) else {
+        return usage_err("couldn't find the app's dirs, so --config and --data must be given");
+    };
+
+    let paths = Paths::new(config_dir, data_dir);
+
+    // the app writes out its league setup the first time it runs, so do the same for its dirs
+    if args.config_dir.is_none() {
+        paths.ensure_default_leagues_config()?;
+    }
+
+    Ok(paths)
+}
+
+/// Sets up a context for the game `args` point at, loading it the way the app would
+async fn open(args: &Args) -> CliResult<OakenCtx> {
+    let ctx = OakenCtx::new(paths(args)?);
+
+    match &args.source {
+        Source::LastSave => SaveHandlers::resume(&ctx).await?,
+        Source::Save(save_id) => {
+            if !ctx.paths.get_save_db_path(*save_id).exists() {
+                return Err(OakenError::not_found("save", save_id).into());
+            }
+
+            SaveHandlers::load(&ctx, *save_id).await?;
+        }
+        Source::Db(path) => {
+            ctx.init_pool(path).await?;
+
+            if League::get_all(&ctx).await?.is_empty() {
+                GameHandlers::restart_game(&ctx).await?;
+            }
+        }
+    }
+
+    Ok(ctx)
+}
+
+async fn advance(ctx: &OakenCtx, how: &Advance) -> OakenResult<()> {
+    let GameState { year, wk_no, .. } = GameState::get(ctx).await?;
+    let (mut now_year, mut now_wk) = (year, wk_no);
+    let mut played = 0;
+
+    loop {
+        match how {
+            Advance::Weeks(weeks) if played >= *weeks => break,
+            Advance::ToEnd if now_year != year => {
+                println!("Finished the {year} season");
+                break;
+            }
+            _ => {}
+        }
+
+        GameHandlers::next_week(ctx).await?;
+        played += 1;
+
+        let now = GameState::get(ctx).await?;
+
+        // a season with no fixtures never moves on, so there's nothing more to play
+        if (now.year, now.wk_no) == (now_year, now_wk) {
+            break;
+        }
+
+        (now_year, now_wk) = (now.year, now.wk_no);
+    }
+
+    println!("Advanced from week {wk_no} of {year} to week {now_wk} of {now_year}");
+
+    Ok(())
+}
+
+fn matches(query: &str, candidates: &[&str]) -> bool {
+    candidates
+        .iter()
+        .any(|candidate| candidate.eq_ignore_ascii_case(query))
+}
+
+async fn standings(
+    ctx: &OakenCtx, json: bool, year: Option<i32>, league: Option<&str>, tier: Option<&str>,
+) -> OakenResult<()> {
+    let year = match year {
+        Some(year) => year,
+        None => GameState::get(ctx).await?.year,
+    };
+
+    let leagues = LeagueInfo::get_all(ctx)
+        .await?
+        .into_iter()
+        .filter(|info| league.is_none_or(|league| matches(league, &[&info.name, &info.abbr])))
+        .collect_vec();
+
+    if let (Some(league), true) = (league, leagues.is_empty()) {
+        return Err(OakenError::not_found("league", league));
+    }
+
+    let divisions = leagues
+        .iter()
+        .flat_map(|league| {
+            league
+                .tiers
+                .iter()
+                .sorted_by_key(|tier| tier.rank)
+                .map(move |tier| (league, tier))
+        })
+        .filter(|(_, info)| {
+            tier.is_none_or(|tier| matches(tier, &[&info.name, &info.rank.to_string()]))
+        })
+        .collect_vec();
+
+    if let (Some(tier), true) = (tier, divisions.is_empty()) {
+        return Err(OakenError::not_found("tier", tier));
+    }
+
+    let mut tables = Standing::get_by_tier(ctx, &year).await?;
+    let tables = divisions
+        .into_iter()
+        .filter_map(|(league, tier)| Some((league, tier, tables.remove(&tier.id)?)))
+        .collect_vec();
+
+    if json {
+        let rows = tables
+            .into_iter()
+            .flat_map(|(_, _, table)| table)
+            .collect_vec();
+        println!("{}", serde_json::to_string_pretty(&rows).unwrap());
+
+        return Ok(());
+    }
+
+    for (league, tier, table) in tables {
+        println!("{} - {} ({year})", league.name, tier.name);
+        println!(
+            "{:>3}  {:<30} {:>3} {:>3} {:>3} {:>3} {:>5} {:>5} {:>5} {:>4}",
+            "Pos", "Team", "P", "W", "D", "L", "PF", "PA", "+/-", "Pts"
+        );
+
+        for standing in table {
+            println!(
+                "{:>3}  {:<30} {:>3} {:>3} {:>3} {:>3} {:>5} {:>5} {:>+5} {:>4}",
+                standing.rank,
+                standing.team_name,
+                standing.wins + standing.draws + standing.losses,
+                standing.wins,
+                standing.draws,
+                standing.losses,
+                standing.points_for,
+                standing.points_against,
+                standing.points_for - standing.points_against,
+                standing.points,
+            );
+        }
+
+        println!();
+    }
+
+    Ok(())
+}
+
+async fn schedule(ctx: &OakenCtx, json: bool, team: &str, year: Option<i32>) -> OakenResult<()> {
+    let teams = TeamInfo::get_all(ctx).await?;
+    let Some(info) = teams
+        .iter()
+        .find(|info| info.id.to_string() == team || matches(team, &[&info.name, &info.abbr]))
+    else {
+        return Err(OakenError::not_found("team", team));
+    };
+
+    let year = match year {
+        Some(year) => year,
+        None => GameState::get(ctx).await?.year,
+    };
+
+    let season = Season::get_by_year(ctx, &year).await?;
+    let matchups = Matchup::get_with_teamid(ctx, &info.id)
+        .await?
+        .into_iter()
+        .filter(|matchup| matchup.season_id == season.id)
+        .sorted_by_key(|matchup| matchup.wk_no)
+        .collect_vec();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&matchups).unwrap());
+
+        return Ok(());
+    }
+
+    println!("{} ({}), {year}", info.name, info.abbr);
+    println!(" Wk        {:<36} Result", "Opponent");
+
+    for matchup in matchups {
+        let home = matchup.home_team_id == info.id;
+        let opponent_id = if home {
+            matchup.away_team_id
+        } else {
+            matchup.home_team_id
+        };
+        let opponent = teams
+            .iter()
+            .find(|other| other.id == opponent_id)
+            .map_or_else(
+                || format!("Team {opponent_id}"),
+                |other| format!("{} ({})", other.name, other.abbr),
+            );
+
+        let scores = matchup.home_team_score.zip(matchup.away_team_score);
+        let result = match (matchup.outcome_for(&info.id), scores) {
+            (Some(outcome), Some((home_score, away_score))) => {
+                let letter = match outcome {
+                    Outcome::Win => "W",
+                    Outcome::Draw => "D",
+                    Outcome::Loss => "L",
+                };
+                let (ours, theirs) = if home {
+                    (home_score, away_score)
+                } else {
+                    (away_score, home_score)
+                };
+
+                format!("{letter} {ours}-{theirs}")
+            }
+            _ => "-".to_owned(),
+        };
+
+        println!(
+            "{:>3}  {:<4}  {:<36} {}",
+            matchup.wk_no,
+            if home { "home" } else { "away" },
+            opponent,
+            result
+        );
+    }
+
+    Ok(())
+}
+
+async fn simulate(
+    args: &Args, seasons: u32, seed: Option<u64>, jobs: Option<usize>, out: Option<&PathBuf>,
+) -> CliResult<()> {
+    let config_dir = paths(args)?.get_default_config_dir();
+    let seed = seed.unwrap_or_else(rand::random);
+    let jobs =
+        jobs.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |jobs| jobs.get()));
+
+    eprintln!("Simulating {seasons} seasons from seed {seed}");
+
+    let report = BatchHandlers::simulate(&config_dir, seasons, seed, jobs, |done| {
+        eprint!("\rPlayed {done} of {seasons}");
+        let _ = std::io::stderr().flush();
+    })
+    .await?;
+    eprintln!();
+
+    let output = if args.json {
+        serde_json::to_string_pretty(&report).unwrap() + "\n"
+    } else {
+        report.to_csv()
+    };
+
+    match out {
+        Some(path) => {
+            fs::write(path, output).map_err(OakenError::from)?;
+            eprintln!("Wrote the report to {}", path.display());
+        }
+        None => print!("{output}"),
+    }
+
+    Ok(())
+}
+
+async fn run(args: Args) -> CliResult<()> {
+    match &args.command {
+        Command::Help => {
+            println!("{USAGE}");
+            return Ok(());
+        }
+        Command::Simulate {
+            seasons,
+            seed,
+            jobs,
+            out,
+        } => return simulate(&args, *seasons, *seed, *jobs, out.as_ref()).await,
+        _ => {}
+    }
+
+    let ctx = open(&args).await?;
+
+    match &args.command {
+        Command::Restart => {
+            GameHandlers::restart_game(&ctx).await?;
+
+            let GameState { year, .. } = GameState::get(&ctx).await?;
+            println!("Started a new game in {year}");
+        }
+        Command::Advance(how) => advance(&ctx, how).await?,
+        Command::Standings { year, league, tier } => {
+            standings(&ctx, args.json, *year, league.as_deref(), tier.as_deref()).await?
+        }
+        Command::Schedule { team, year } => schedule(&ctx, args.json, team, *year).await?,
+        Command::Export(path) => {
+            SaveHandlers::export(&ctx, path).await?;
+            println!("Exported to {}", path.display());
+        }
+        Command::Tui => tui::run(&ctx).await?,
+        Command::Help | Command::Simulate { .. } => unreachable!(),
+    }
+
+    ctx.close_pool().await;
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let result = match Args::parse(std::env::args().skip(1).collect()) {
+        Ok(args) => run(args).await,
+        Err(err) => Err(err),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(CliError::Usage(msg)) => {
+            eprintln!("error: {msg}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
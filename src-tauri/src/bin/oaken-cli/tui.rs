@@ -0,0 +1,561 @@
+//! A terminal frontend with the same Snapshot, Schedule and Standings tabs as the app's home page,
+//! plus a page for each team, so a game can be played over SSH or without a webview
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use oaken::{
+    ctx::OakenCtx,
+    error::OakenResult,
+    handlers::game::GameHandlers,
+    models::{
+        game::GameState,
+        league::LeagueInfo,
+        matchup::{Matchup, Outcome},
+        schedule::Schedule,
+        season::Season,
+        standings::Standing,
+        team::TeamInfo,
+        tier::Tier,
+    },
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, Paragraph, Row, Table, TableState, Tabs},
+    DefaultTerminal, Frame,
+};
+
+const KEYS: &str =
+    "←/→ tab  [/] division  ↑/↓ select  enter home team  a away team  n next week  q quit";
+const TEAM_KEYS: &str = "↑/↓ select  enter opponent  esc back  n next week  q quit";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Snapshot,
+    Schedule,
+    Standings,
+}
+
+impl Tab {
+    const ALL: [Self; 3] = [Self::Snapshot, Self::Schedule, Self::Standings];
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Snapshot => "Snapshot",
+            Self::Schedule => "Schedule",
+            Self::Standings => "Standings",
+        }
+    }
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|tab| *tab == self).unwrap()
+    }
+}
+
+/// Everything the tabs show, fetched again whenever the game moves on
+struct Data {
+    game: GameState,
+    season: Season,
+    leagues: Vec<LeagueInfo>,
+    teams: HashMap<i32, TeamInfo>,
+    schedules: Vec<Schedule>,
+    standings: Vec<Standing>,
+}
+
+impl Data {
+    async fn load(ctx: &OakenCtx) -> OakenResult<Self> {
+        let game = GameState::get(ctx).await?;
+
+        Ok(Self {
+            season: Season::get_by_year(ctx, &game.year).await?,
+            leagues: LeagueInfo::get_all(ctx).await?,
+            teams: TeamInfo::get_all(ctx)
+                .await?
+                .into_iter()
+                .map(|team| (team.id, team))
+                .collect(),
+            schedules: Schedule::get_all_by_year(ctx, &game.year).await?,
+            standings: Standing::get(ctx, &game.year).await?,
+            game,
+        })
+    }
+
+    fn team_name(&self, team_id: i32) -> String {
+        self.teams
+            .get(&team_id)
+            .map_or_else(|| format!("Team {team_id}"), |team| team.name.clone())
+    }
+
+    /// Every tier of every league, in the order they're cycled through
+    fn divisions(&self) -> Vec<(&LeagueInfo, &Tier)> {
+        self.leagues
+            .iter()
+            .flat_map(|league| {
+                league
+                    .tiers
+                    .iter()
+                    .sorted_by_key(|tier| tier.rank)
+                    .map(move |tier| (league, tier))
+            })
+            .collect_vec()
+    }
+}
+
+/// A team's page, showing what `getTeamMatchups` returns for it
+struct TeamPage {
+    team_id: i32,
+    matchups: Vec<Matchup>,
+    table: TableState,
+}
+
+impl TeamPage {
+    async fn load(ctx: &OakenCtx, team_id: i32) -> OakenResult<Self> {
+        let matchups = Matchup::get_with_teamid(ctx, &team_id)
+            .await?
+            .into_iter()
+            .sorted_by_key(|matchup| (matchup.season_id, matchup.wk_no))
+            .collect_vec();
+
+        Ok(Self {
+            team_id,
+            matchups,
+            table: TableState::default().with_selected(0),
+        })
+    }
+}
+
+struct App<'a> {
+    ctx: &'a OakenCtx,
+    data: Data,
+    tab: Tab,
+    /// Index into [`Data::divisions`]
+    division: usize,
+    table: TableState,
+    /// Team pages opened on top of the tabs, the one on show last
+    pages: Vec<TeamPage>,
+    status: String,
+    quit: bool,
+}
+
+impl<'a> App<'a> {
+    async fn new(ctx: &'a OakenCtx) -> OakenResult<Self> {
+        Ok(Self {
+            ctx,
+            data: Data::load(ctx).await?,
+            tab: Tab::Snapshot,
+            division: 0,
+            table: TableState::default().with_selected(0),
+            pages: vec![],
+            status: String::new(),
+            quit: false,
+        })
+    }
+
+    /// The schedule for the division on show
+    fn schedule(&self) -> Vec<&Matchup> {
+        let divisions = self.data.divisions();
+        let Some((league, tier)) = divisions.get(self.division) else {
+            return vec![];
+        };
+
+        self.data
+            .schedules
+            .iter()
+            .filter(|schedule| schedule.league_id == league.id && schedule.tier_id == tier.id)
+            .flat_map(|schedule| &schedule.matchups)
+            .sorted_by_key(|matchup| matchup.wk_no)
+            .collect_vec()
+    }
+
+    /// This week's fixtures for the division on show, followed by last week's results
+    fn snapshot(&self) -> Vec<&Matchup> {
+        let wk_no = self.data.game.wk_no;
+        let schedule = self.schedule();
+
+        schedule
+            .iter()
+            .filter(|matchup| matchup.wk_no == wk_no)
+            .chain(schedule.iter().filter(|matchup| matchup.wk_no == wk_no - 1))
+            .copied()
+            .collect_vec()
+    }
+
+    fn table_rows(&self) -> Vec<&Standing> {
+        let divisions = self.data.divisions();
+        let Some((league, tier)) = divisions.get(self.division) else {
+            return vec![];
+        };
+
+        self.data
+            .standings
+            .iter()
+            .filter(|standing| standing.league_id == league.id && standing.tier_id == tier.id)
+            .collect_vec()
+    }
+
+    /// The team the selected row points at, taking the away side of a matchup if `away`
+    fn selected_team(&self, away: bool) -> Option<i32> {
+        let selected = self.table.selected()?;
+        let pick = |matchup: &&Matchup| {
+            if away {
+                matchup.away_team_id
+            } else {
+                matchup.home_team_id
+            }
+        };
+
+        match self.tab {
+            Tab::Snapshot => self.snapshot().get(selected).map(pick),
+            Tab::Schedule => self.schedule().get(selected).map(pick),
+            Tab::Standings => self
+                .table_rows()
+                .get(selected)
+                .map(|standing| standing.team_id),
+        }
+    }
+
+    async fn handle_key(&mut self, key: KeyEvent) -> OakenResult<()> {
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Char('n') => self.next_week().await?,
+            _ if !self.pages.is_empty() => self.handle_page_key(key).await?,
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Right | KeyCode::Tab => self.switch_tab(1),
+            KeyCode::Left | KeyCode::BackTab => self.switch_tab(Tab::ALL.len() - 1),
+            KeyCode::Char(n @ '1'..='3') => {
+                self.tab = Tab::ALL[n as usize - '1' as usize];
+                self.table.select(Some(0));
+            }
+            KeyCode::Char(']') => self.switch_division(1),
+            KeyCode::Char('[') => self.switch_division(self.data.divisions().len().max(1) - 1),
+            KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+            KeyCode::Enter | KeyCode::Char('a') => {
+                if let Some(team_id) = self.selected_team(key.code == KeyCode::Char('a')) {
+                    self.pages.push(TeamPage::load(self.ctx, team_id).await?);
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    async fn handle_page_key(&mut self, key: KeyEvent) -> OakenResult<()> {
+        let Some(page) = self.pages.last_mut() else {
+            return Ok(());
+        };
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Backspace => {
+                self.pages.pop();
+            }
+            KeyCode::Down | KeyCode::Char('j') => page.table.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => page.table.select_previous(),
+            KeyCode::Enter => {
+                let opponent = page
+                    .table
+                    .selected()
+                    .and_then(|selected| page.matchups.get(selected))
+                    .map(|matchup| {
+                        if matchup.home_team_id == page.team_id {
+                            matchup.away_team_id
+                        } else {
+                            matchup.home_team_id
+                        }
+                    });
+
+                if let Some(team_id) = opponent {
+                    self.pages.push(TeamPage::load(self.ctx, team_id).await?);
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn switch_tab(&mut self, by: usize) {
+        self.tab = Tab::ALL[(self.tab.index() + by) % Tab::ALL.len()];
+        self.table.select(Some(0));
+    }
+
+    fn switch_division(&mut self, by: usize) {
+        self.division = (self.division + by) % self.data.divisions().len().max(1);
+        self.table.select(Some(0));
+    }
+
+    async fn next_week(&mut self) -> OakenResult<()> {
+        let GameState { year, wk_no, .. } = self.data.game;
+
+        GameHandlers::next_week(self.ctx).await?;
+        self.data = Data::load(self.ctx).await?;
+
+        let GameState {
+            year: now_year,
+            wk_no: now_wk,
+            ..
+        } = self.data.game;
+
+        self.status = if now_year != year {
+            format!("Finished the {year} season")
+        } else if now_wk != wk_no {
+            format!("Played week {wk_no}")
+        } else {
+            "There are no more weeks to play".to_owned()
+        };
+
+        // the pages beneath the one on show are refetched as they're returned to
+        for page in &mut self.pages {
+            let selected = page.table.selected();
+            *page = TeamPage::load(self.ctx, page.team_id).await?;
+            page.table.select(selected);
+        }
+
+        Ok(())
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let keys = if self.pages.is_empty() {
+            KEYS
+        } else {
+            TEAM_KEYS
+        };
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                keys.dim(),
+                "  ".into(),
+                self.status.as_str().bold(),
+            ])),
+            footer,
+        );
+
+        if !self.pages.is_empty() {
+            return self.draw_team_page(frame, header, body);
+        }
+
+        let divisions = self.data.divisions();
+        let division = divisions
+            .get(self.division)
+            .map_or_else(String::new, |(league, tier)| {
+                format!("{} // {}", league.name, tier.name)
+            });
+        let title = format!(
+            " Oaken - {division} - week {} of {}, {} ",
+            self.data.game.wk_no, self.data.season.end_wk, self.data.game.year
+        );
+
+        frame.render_widget(
+            Tabs::new(Tab::ALL.map(Tab::title))
+                .select(self.tab.index())
+                .highlight_style(Style::new().bold().reversed())
+                .block(Block::bordered().title(title)),
+            header,
+        );
+
+        match self.tab {
+            Tab::Snapshot => {
+                let title = format!(" Week {} Snapshot ", self.data.game.wk_no);
+                let rows = self
+                    .snapshot()
+                    .into_iter()
+                    .map(|matchup| self.matchup_row(matchup, "TBD"));
+                let table = matchup_table(rows.collect_vec(), title);
+
+                frame.render_stateful_widget(table, body, &mut self.table);
+            }
+            Tab::Schedule => {
+                let rows = self
+                    .schedule()
+                    .into_iter()
+                    .map(|matchup| self.matchup_row(matchup, "-"));
+                let table = matchup_table(rows.collect_vec(), " Season Schedule ".to_owned());
+
+                frame.render_stateful_widget(table, body, &mut self.table);
+            }
+            Tab::Standings => {
+                let rows = self.table_rows().into_iter().map(|standing| {
+                    Row::new([
+                        format!("{}.", standing.rank),
+                        standing.team_name.clone(),
+                        standing.wins.to_string(),
+                        standing.losses.to_string(),
+                        standing.draws.to_string(),
+                        standing
+                            .win_percent
+                            .map_or_else(|| "-".to_owned(), |percent| format!("{percent:.3}")),
+                        standing.points_for.to_string(),
+                        standing.points_against.to_string(),
+                        standing.streak.to_string(),
+                        standing.points.to_string(),
+                    ])
+                });
+                let widths = [
+                    Constraint::Length(4),
+                    Constraint::Fill(1),
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Length(5),
+                    Constraint::Length(5),
+                    Constraint::Length(5),
+                    Constraint::Length(4),
+                    Constraint::Length(4),
+                ];
+                let table = Table::new(rows.collect_vec(), widths)
+                    .header(
+                        Row::new([
+                            "Pos.", "Team", "W", "L", "T", "%", "PF", "PA", "Strk", "Pts",
+                        ])
+                        .bold(),
+                    )
+                    .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+                    .block(Block::bordered().title(format!(" {} Standings ", self.data.game.year)));
+
+                frame.render_stateful_widget(table, body, &mut self.table);
+            }
+        }
+    }
+
+    fn matchup_row(&self, matchup: &Matchup, unplayed: &'static str) -> Row<'static> {
+        let result = matchup
+            .away_team_score
+            .zip(matchup.home_team_score)
+            .map_or_else(
+                || unplayed.to_owned(),
+                |(away, home)| format!("{away} - {home}"),
+            );
+
+        Row::new([
+            matchup.wk_no.to_string(),
+            self.data.team_name(matchup.away_team_id),
+            format!("@ {}", self.data.team_name(matchup.home_team_id)),
+            result,
+        ])
+    }
+
+    fn draw_team_page(&mut self, frame: &mut Frame, header: Rect, body: Rect) {
+        let Some(page) = self.pages.last_mut() else {
+            return;
+        };
+
+        let heading = match self.data.teams.get(&page.team_id) {
+            Some(team) => format!(
+                " {} ({}) - {} // {} - {}, est. {} - skill {} ",
+                team.name,
+                team.abbr,
+                team.league.name,
+                team.tier.name,
+                team.city,
+                team.founded,
+                team.skill
+            ),
+            None => format!(" Team {} ", page.team_id),
+        };
+        frame.render_widget(Block::bordered().title(heading), header);
+
+        let outcomes = page
+            .matchups
+            .iter()
+            .filter_map(|matchup| matchup.outcome_for(&page.team_id))
+            .counts();
+        let record = format!(
+            " Win/Loss/Draw: {} / {} / {} ",
+            outcomes.get(&Outcome::Win).unwrap_or(&0),
+            outcomes.get(&Outcome::Loss).unwrap_or(&0),
+            outcomes.get(&Outcome::Draw).unwrap_or(&0),
+        );
+
+        let rows = page.matchups.iter().map(|matchup| {
+            let scores = matchup.away_team_score.zip(matchup.home_team_score);
+            let result = match (matchup.outcome_for(&page.team_id), scores) {
+                (Some(outcome), Some((away, home))) => {
+                    let verdict = match outcome {
+                        Outcome::Win => "Win",
+                        Outcome::Draw => "Draw",
+                        Outcome::Loss => "Loss",
+                    };
+
+                    format!("{verdict} ({away} - {home})")
+                }
+                _ => "-".to_owned(),
+            };
+
+            Row::new([
+                matchup.wk_no.to_string(),
+                self.data.team_name(matchup.away_team_id),
+                format!("@ {}", self.data.team_name(matchup.home_team_id)),
+                result,
+                matchup
+                    .attendance
+                    .map_or_else(|| "-".to_owned(), |attendance| attendance.to_string()),
+            ])
+        });
+        let widths = [
+            Constraint::Length(4),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+            Constraint::Length(14),
+            Constraint::Length(10),
+        ];
+        let table = Table::new(rows.collect_vec(), widths)
+            .header(Row::new(["Wk", "Away Team", "Home Team", "Result", "Attendance"]).bold())
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .block(
+                Block::bordered()
+                    .title(" Schedule ")
+                    .title_bottom(Line::from(record).right_aligned()),
+            );
+
+        frame.render_stateful_widget(table, body, &mut page.table);
+    }
+}
+
+fn matchup_table(rows: Vec<Row<'static>>, title: String) -> Table<'static> {
+    let widths = [
+        Constraint::Length(4),
+        Constraint::Fill(1),
+        Constraint::Fill(1),
+        Constraint::Length(9),
+    ];
+
+    Table::new(rows, widths)
+        .header(Row::new(["Week", "Away Team", "Home Team", "Results"]).bold())
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .block(Block::bordered().title(title))
+}
+
+async fn run_app(ctx: &OakenCtx, terminal: &mut DefaultTerminal) -> OakenResult<()> {
+    let mut app = App::new(ctx).await?;
+
+    while !app.quit {
+        terminal.draw(|frame| app.draw(frame))?;
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                app.handle_key(key).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Takes over the terminal until the player quits, handing it back however the app exits
+pub async fn run(ctx: &OakenCtx) -> OakenResult<()> {
+    let mut terminal = ratatui::init();
+    let result = run_app(ctx, &mut terminal).await;
+    ratatui::restore();
+
+    result
+}
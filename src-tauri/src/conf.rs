@@ -1,4 +1,6 @@
 use itertools::Itertools;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -11,7 +13,21 @@ pub struct TeamConfigItem {
     pub name: String,
     pub skill: i32,
     pub tier: String,
-    pub league: String
+    pub league: String,
+    #[serde(default = "TeamConfigItem::default_capacity")]
+    pub capacity: i32,
+    pub abbr: Option<String>,
+    pub city: Option<String>,
+    pub founded: Option<i32>,
+    pub primary_color: Option<String>,
+    pub secondary_color: Option<String>,
+}
+
+impl TeamConfigItem {
+    /// Stadium capacity for teams from configs written before capacities were tracked
+    fn default_capacity() -> i32 {
+        20_000
+    }
 }
 
 impl TryFrom<TeamConfig> for String {
@@ -25,6 +41,8 @@ impl TryFrom<TeamConfig> for String {
 pub struct LeagueConfig {
     pub leagues: Vec<LeagueConfigItem>,
     pub tiers: Vec<TierConfigItem>,
+    #[serde(default)]
+    pub skill: SkillConfig,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -48,6 +66,37 @@ pub struct TierConfigItem {
     pub league: String,
 }
 
+/// The normal distribution generated teams draw their skill from. Each tier below the top is
+/// centered `tier_step` lower than the one above it
+#[derive(Serialize, Deserialize)]
+pub struct SkillConfig {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub tier_step: f64,
+}
+
+impl Default for SkillConfig {
+    fn default() -> Self {
+        Self {
+            mean: 70.0,
+            std_dev: 12.0,
+            tier_step: 10.0,
+        }
+    }
+}
+
+impl SkillConfig {
+    pub fn sample<R: Rng>(&self, tier_rank: i32, rng: &mut R) -> i32 {
+        let mean = self.mean - self.tier_step * (tier_rank - 1) as f64;
+
+        Normal::new(mean, self.std_dev)
+            .map(|dist| dist.sample(rng))
+            .unwrap_or(mean)
+            .round()
+            .clamp(1.0, 100.0) as i32
+    }
+}
+
 impl Default for LeagueConfig {
     fn default() -> Self {
         let leagues: Vec<LeagueConfigItem> = [
@@ -74,7 +123,11 @@ impl Default for LeagueConfig {
             })
             .collect_vec();
 
-        Self { leagues, tiers }
+        Self {
+            leagues,
+            tiers,
+            skill: SkillConfig::default(),
+        }
     }
 }
 
@@ -0,0 +1,123 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex as StdMutex, MutexGuard, RwLock},
+};
+
+use futures::Stream;
+use rand::{rngs::StdRng, SeedableRng};
+use tokio::sync::{broadcast, Mutex};
+
+use crate::{
+    error::{OakenError, OakenResult},
+    paths::Paths,
+    shared::{
+        emit::EmitMsg,
+        sql::{establish_connection, establish_in_memory, SqlitePoolWrapper},
+    },
+};
+
+/// How many events a slow subscriber can fall behind by before it starts missing them
+const EVENT_BUFFER: usize = 64;
+
+/// Everything the game reads and writes through: the database of the save in use, and where saves
+/// and world configs live on disk. Models and handlers take one of these rather than reaching for
+/// globals, so the library runs the same under the app, a CLI or against an in-memory database
+#[derive(Debug)]
+pub struct OakenCtx {
+    pool: RwLock<Option<Arc<SqlitePoolWrapper>>>,
+    pub paths: Paths,
+    /// Held for the whole of anything that changes the week or swaps the save in use, so that
+    /// only one runs at a time and none sees another halfway through
+    pub(crate) advance_lock: Mutex<()>,
+    /// Where every roll of the dice in the game comes from
+    rng: StdMutex<StdRng>,
+    events: broadcast::Sender<EmitMsg>,
+}
+
+impl OakenCtx {
+    /// A context with nothing connected yet. Call [`Self::init_pool`] or load a save before using
+    /// any models with it
+    pub fn new(paths: Paths) -> Self {
+        Self {
+            pool: RwLock::new(None),
+            paths,
+            advance_lock: Mutex::new(()),
+            rng: StdMutex::new(StdRng::from_entropy()),
+            events: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+
+    /// A context backed by a fresh database that only lives as long as it does. Saves can't be
+    /// loaded into it, but every model works as normal
+    pub async fn in_memory(paths: Paths) -> OakenResult<Self> {
+        let ctx = Self::new(paths);
+        let pool = Arc::new(establish_in_memory().await?);
+
+        ctx.pool.write().unwrap().replace(pool);
+
+        Ok(ctx)
+    }
+
+    /// Restarts the dice from `seed`, so that the same game played from here plays out the same way
+    /// every time
+    pub fn reseed(&self, seed: u64) {
+        *self.rng.lock().unwrap() = StdRng::seed_from_u64(seed);
+    }
+
+    /// The dice to roll. Don't hold on to them across an `.await`
+    pub fn rng(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap()
+    }
+
+    /// Tells anyone following [`Self::events`] that `msg` happened
+    pub fn emit(&self, msg: EmitMsg) {
+        // nobody listening is fine, the CLI never does
+        let _ = self.events.send(msg);
+    }
+
+    /// Every event emitted from now on, for as long as the stream is kept
+    pub fn events(&self) -> impl Stream<Item = EmitMsg> + Send + Sync + 'static {
+        futures::stream::unfold(self.events.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(msg) => return Some((msg, rx)),
+                    // a subscriber that fell behind skips what it missed rather than hanging up
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    /// The database of the save in use. Fails while there isn't one, such as after the active save
+    /// has been deleted and before another is loaded
+    pub fn pool(&self) -> OakenResult<Arc<SqlitePoolWrapper>> {
+        self.pool
+            .read()
+            .unwrap()
+            .clone()
+            .ok_or(OakenError::NoSaveOpen)
+    }
+
+    /// Connects to the database at `db_path`, replacing whichever pool was in use before. The old
+    /// pool is closed once any queries still running on it have finished
+    pub async fn init_pool(&self, db_path: &Path) -> OakenResult<()> {
+        let pool = Arc::new(establish_connection(db_path).await?);
+        let prev = self.pool.write().unwrap().replace(pool);
+
+        if let Some(prev) = prev {
+            prev.close().await;
+        }
+
+        Ok(())
+    }
+
+    /// Closes the pool in use, leaving nothing connected until the next [`Self::init_pool`]
+    pub async fn close_pool(&self) {
+        let prev = self.pool.write().unwrap().take();
+
+        if let Some(prev) = prev {
+            prev.close().await;
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::{fmt::Display, io};
+
+use rspc::ErrorCode;
+
+use crate::handlers::save::ArchiveError;
+
+#[derive(Debug)]
+pub enum OakenError {
+    /// A query failed to run
+    Database(sqlx::Error),
+    /// Nothing of the kind `kind` could be found by `key`
+    NotFound {
+        kind: &'static str,
+        key: String,
+    },
+    Io(io::Error),
+    /// A leagues or teams config couldn't be read or written
+    Config(String),
+    Archive(ArchiveError),
+    /// A request's arguments don't make sense
+    InvalidArgument(String),
+    /// There is no save to read from or write to
+    NoSaveOpen,
+}
+
+pub type OakenResult<T> = Result<T, OakenError>;
+
+impl OakenError {
+    pub fn not_found(kind: &'static str, key: impl Display) -> Self {
+        Self::NotFound {
+            kind,
+            key: key.to_string(),
+        }
+    }
+}
+
+impl Display for OakenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Database(err) => write!(f, "database error: {err}"),
+            Self::NotFound { kind, key } => write!(f, "{kind} {key} not found"),
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Config(msg) => write!(f, "invalid config: {msg}"),
+            Self::Archive(err) => write!(f, "{err}"),
+            Self::InvalidArgument(msg) => write!(f, "invalid argument: {msg}"),
+            Self::NoSaveOpen => write!(f, "no save is open"),
+        }
+    }
+}
+
+impl std::error::Error for OakenError {}
+
+impl From<sqlx::Error> for OakenError {
+    fn from(value: sqlx::Error) -> Self {
+        match value {
+            // a query that got hold of the pool just before the save was closed
+            sqlx::Error::PoolClosed => Self::NoSaveOpen,
+            value => Self::Database(value),
+        }
+    }
+}
+
+impl From<io::Error> for OakenError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<toml::de::Error> for OakenError {
+    fn from(value: toml::de::Error) -> Self {
+        Self::Config(value.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for OakenError {
+    fn from(value: toml::ser::Error) -> Self {
+        Self::Config(value.to_string())
+    }
+}
+
+impl From<ArchiveError> for OakenError {
+    fn from(value: ArchiveError) -> Self {
+        Self::Archive(value)
+    }
+}
+
+impl From<OakenError> for rspc::Error {
+    fn from(value: OakenError) -> Self {
+        let code = match value {
+            OakenError::NotFound { .. } => ErrorCode::NotFound,
+            OakenError::Archive(_) | OakenError::Config(_) | OakenError::InvalidArgument(_) => {
+                ErrorCode::BadRequest
+            }
+            OakenError::NoSaveOpen => ErrorCode::PreconditionFailed,
+            OakenError::Database(_) | OakenError::Io(_) => ErrorCode::InternalServerError,
+        };
+
+        rspc::Error::new(code, value.to_string())
+    }
+}
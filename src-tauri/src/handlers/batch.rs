@@ -0,0 +1,417 @@
+use std::{collections::HashMap, path::Path};
+
+use futures::{stream, StreamExt};
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    ctx::OakenCtx,
+    error::OakenResult,
+    models::{
+        game::GameState,
+        matchup::{Matchup, Outcome},
+        season::Season,
+        standings::Standing,
+        team::TeamInfo,
+    },
+    paths::Paths,
+};
+
+use super::game::GameHandlers;
+
+/// How wide each band of team skill is when averaging points
+const SKILL_BAND: i32 = 10;
+
+/// How wide each band of skill gap is when counting upsets
+const GAP_BAND: i32 = 5;
+
+/// Sums up many seasons played out from the same world, for checking how the match engine is
+/// balanced
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReport {
+    pub seasons: u32,
+    pub seed: u64,
+    /// The year each season was played as
+    pub year: i32,
+    /// Every team, most titles first
+    pub teams: Vec<TeamTally>,
+    pub points_by_skill: Vec<SkillBand>,
+    pub upsets_by_gap: Vec<GapBand>,
+    /// Share of matchups between unevenly skilled sides that the weaker side won
+    pub upset_rate: f64,
+    /// Mean Spearman correlation between the skill order and final order of each table. 1 means
+    /// every table finished in skill order
+    pub skill_rank_correlation: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamTally {
+    pub team_id: i32,
+    pub name: String,
+    pub league: String,
+    pub tier: String,
+    pub skill: i32,
+    /// Seasons the team finished top of its tier
+    pub titles: u32,
+    pub title_share: f64,
+    pub avg_points: f64,
+    pub avg_rank: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillBand {
+    pub min_skill: i32,
+    pub max_skill: i32,
+    /// Team seasons that fell in this band
+    pub samples: u32,
+    pub avg_points: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GapBand {
+    pub min_gap: i32,
+    pub max_gap: i32,
+    pub matchups: u32,
+    pub upsets: u32,
+    pub upset_rate: f64,
+}
+
+/// What a single season left behind
+struct SeasonSample {
+    teams: Vec<TeamInfo>,
+    year: i32,
+    standings: Vec<Standing>,
+    /// The skill gap of every played matchup between unevenly skilled sides, and whether the
+    /// weaker side won it
+    meetings: Vec<(i32, bool)>,
+}
+
+pub struct BatchHandlers;
+
+impl BatchHandlers {
+    /// Plays `seasons` seasons of the world configured in `config_dir`, each in a game of its own
+    /// held in memory. The world is generated from `seed` so every season starts from the same
+    /// teams, and season `i` is then played with `seed + i`, so the same arguments always give the
+    /// same report. Up to `jobs` seasons are played at once, with `on_progress` told how many are
+    /// done as each one finishes
+    pub async fn simulate(
+        config_dir: &Path, seasons: u32, seed: u64, jobs: usize, mut on_progress: impl FnMut(u32),
+    ) -> OakenResult<BatchReport> {
+        let mut samples = stream::iter(0..seasons)
+            .map(|i| {
+                let config_dir = config_dir.to_owned();
+
+                tokio::spawn(async move {
+                    let sample = Self::play_season(&config_dir, seed, seed.wrapping_add(i as u64));
+                    (i, sample.await)
+                })
+            })
+            .buffer_unordered(jobs.max(1))
+            .map(|joined| joined.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic())))
+            .enumerate()
+            .map(|(done, (i, sample))| {
+                on_progress(done as u32 + 1);
+                sample.map(|sample| (i, sample))
+            })
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<OakenResult<Vec<_>>>()?;
+
+        // seasons finish in any order, so put them back in seed order before adding anything up
+        samples.sort_by_key(|(i, _)| *i);
+        let samples = samples.into_iter().map(|(_, sample)| sample).collect_vec();
+
+        Ok(Self::summarize(&samples, seasons, seed))
+    }
+
+    /// Generates the world from `world_seed` in a fresh in-memory game, then plays its first
+    /// season through with `season_seed`
+    async fn play_season(
+        config_dir: &Path, world_seed: u64, season_seed: u64,
+    ) -> OakenResult<SeasonSample> {
+        // nothing here is saved, so the data dir is never touched
+        let ctx = OakenCtx::in_memory(Paths::new(config_dir, config_dir)).await?;
+
+        ctx.reseed(world_seed);
+        GameHandlers::restart_game(&ctx).await?;
+
+        let teams = TeamInfo::get_all(&ctx).await?;
+        let GameState { year, .. } = GameState::get(&ctx).await?;
+
+        ctx.reseed(season_seed);
+
+        loop {
+            let before = GameState::get(&ctx).await?;
+            GameHandlers::next_week(&ctx).await?;
+            let now = GameState::get(&ctx).await?;
+
+            // a season with no fixtures never moves on, so stop rather than spin
+            if now.year != year || (now.year, now.wk_no) == (before.year, before.wk_no) {
+                break;
+            }
+        }
+
+        let skills: HashMap<i32, i32> = teams.iter().map(|team| (team.id, team.skill)).collect();
+        let season = Season::get_by_year(&ctx, &year).await?;
+        let meetings = Matchup::get_for_season(&ctx, &season.id)
+            .await?
+            .into_iter()
+            .filter_map(|matchup| {
+                let home = skills[&matchup.home_team_id];
+                let away = skills[&matchup.away_team_id];
+                let underdog = match home.cmp(&away) {
+                    std::cmp::Ordering::Less => matchup.home_team_id,
+                    std::cmp::Ordering::Greater => matchup.away_team_id,
+                    std::cmp::Ordering::Equal => return None,
+                };
+
+                let outcome = matchup.outcome_for(&underdog)?;
+                Some(((home - away).abs(), outcome == Outcome::Win))
+            })
+            .collect_vec();
+
+        let standings = Standing::get(&ctx, &year).await?;
+        ctx.close_pool().await;
+
+        Ok(SeasonSample {
+            teams,
+            year,
+            standings,
+            meetings,
+        })
+    }
+
+    fn summarize(samples: &[SeasonSample], seasons: u32, seed: u64) -> BatchReport {
+        let Some(first) = samples.first() else {
+            return BatchReport {
+                seasons,
+                seed,
+                year: 0,
+                teams: vec![],
+                points_by_skill: vec![],
+                upsets_by_gap: vec![],
+                upset_rate: 0.0,
+                skill_rank_correlation: 0.0,
+            };
+        };
+
+        // every season is played from the same world, so the first one's teams stand for all
+        let skills: HashMap<i32, i32> = first
+            .teams
+            .iter()
+            .map(|team| (team.id, team.skill))
+            .collect();
+        let standings = samples
+            .iter()
+            .flat_map(|sample| &sample.standings)
+            .into_group_map_by(|standing| standing.team_id);
+
+        let teams = first
+            .teams
+            .iter()
+            .map(|team| {
+                let rows = standings.get(&team.id).map_or(&[][..], Vec::as_slice);
+                let titles = rows.iter().filter(|row| row.rank == 1).count() as u32;
+
+                TeamTally {
+                    team_id: team.id,
+                    name: team.name.clone(),
+                    league: team.league.name.clone(),
+                    tier: team.tier.name.clone(),
+                    skill: team.skill,
+                    titles,
+                    title_share: titles as f64 / samples.len() as f64,
+                    avg_points: mean(rows.iter().map(|row| row.points as f64)),
+                    avg_rank: mean(rows.iter().map(|row| row.rank as f64)),
+                }
+            })
+            .sorted_by(|a, b| {
+                b.titles
+                    .cmp(&a.titles)
+                    .then(b.skill.cmp(&a.skill))
+                    .then(a.team_id.cmp(&b.team_id))
+            })
+            .collect_vec();
+
+        let points_by_skill = samples
+            .iter()
+            .flat_map(|sample| &sample.standings)
+            .into_group_map_by(|row| skills[&row.team_id].div_euclid(SKILL_BAND))
+            .into_iter()
+            .sorted_by_key(|(band, _)| *band)
+            .map(|(band, rows)| SkillBand {
+                min_skill: band * SKILL_BAND,
+                max_skill: band * SKILL_BAND + SKILL_BAND - 1,
+                samples: rows.len() as u32,
+                avg_points: mean(rows.iter().map(|row| row.points as f64)),
+            })
+            .collect_vec();
+
+        let meetings = samples
+            .iter()
+            .flat_map(|sample| &sample.meetings)
+            .collect_vec();
+        let upsets_by_gap = meetings
+            .iter()
+            .into_group_map_by(|(gap, _)| gap / GAP_BAND)
+            .into_iter()
+            .sorted_by_key(|(band, _)| *band)
+            .map(|(band, meetings)| {
+                let upsets = meetings.iter().filter(|(_, upset)| *upset).count() as u32;
+
+                GapBand {
+                    // gaps of nothing aren't counted, so the first band starts at one
+                    min_gap: (band * GAP_BAND).max(1),
+                    max_gap: band * GAP_BAND + GAP_BAND - 1,
+                    matchups: meetings.len() as u32,
+                    upsets,
+                    upset_rate: upsets as f64 / meetings.len() as f64,
+                }
+            })
+            .collect_vec();
+        let upset_rate = mean(meetings.iter().map(|(_, upset)| *upset as u8 as f64));
+
+        let skill_rank_correlation = mean(
+            samples
+                .iter()
+                .flat_map(|sample| {
+                    sample
+                        .standings
+                        .iter()
+                        .into_group_map_by(|row| row.tier_id)
+                        .into_values()
+                })
+                .filter_map(|table| {
+                    let skill_order =
+                        ranks(&table.iter().map(|row| -skills[&row.team_id]).collect_vec());
+                    let finish_order = table.iter().map(|row| row.rank as f64).collect_vec();
+
+                    pearson(&skill_order, &finish_order)
+                }),
+        );
+
+        BatchReport {
+            seasons,
+            seed,
+            year: first.year,
+            teams,
+            points_by_skill,
+            upsets_by_gap,
+            upset_rate,
+            skill_rank_correlation,
+        }
+    }
+}
+
+impl BatchReport {
+    /// Flattens the report into `section,key,n,value` rows, where `n` is how many seasons, team
+    /// seasons or matchups the value was taken over. Keeping every figure in one file lets
+    /// spreadsheets and dataframes pivot it however they like
+    pub fn to_csv(&self) -> String {
+        let seasons = self.seasons.to_string();
+        let mut rows = vec![
+            [
+                "upset_rate".to_owned(),
+                "all".to_owned(),
+                self.upsets_by_gap
+                    .iter()
+                    .map(|band| band.matchups)
+                    .sum::<u32>()
+                    .to_string(),
+                self.upset_rate.to_string(),
+            ],
+            [
+                "skill_rank_correlation".to_owned(),
+                "all".to_owned(),
+                seasons.clone(),
+                self.skill_rank_correlation.to_string(),
+            ],
+        ];
+
+        rows.extend(self.teams.iter().flat_map(|team| {
+            [
+                ("skill", "1".to_owned(), team.skill.to_string()),
+                ("titles", seasons.clone(), team.titles.to_string()),
+                ("avg_points", seasons.clone(), team.avg_points.to_string()),
+                ("avg_rank", seasons.clone(), team.avg_rank.to_string()),
+            ]
+            .map(|(section, n, value)| [section.to_owned(), team.name.clone(), n, value])
+        }));
+
+        rows.extend(self.points_by_skill.iter().map(|band| {
+            [
+                "points_by_skill".to_owned(),
+                format!("{}-{}", band.min_skill, band.max_skill),
+                band.samples.to_string(),
+                band.avg_points.to_string(),
+            ]
+        }));
+
+        rows.extend(self.upsets_by_gap.iter().map(|band| {
+            [
+                "upset_rate".to_owned(),
+                format!("{}-{}", band.min_gap, band.max_gap),
+                band.matchups.to_string(),
+                band.upset_rate.to_string(),
+            ]
+        }));
+
+        std::iter::once("section,key,n,value".to_owned())
+            .chain(
+                rows.iter()
+                    .map(|row| row.iter().map(|field| csv_field(field)).join(",")),
+            )
+            .map(|line| line + "\n")
+            .collect()
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, n) = values.fold((0.0, 0), |(sum, n), value| (sum + value, n + 1));
+
+    if n == 0 {
+        0.0
+    } else {
+        sum / n as f64
+    }
+}
+
+/// Ranks each value from 1 up, sharing the average rank between ties
+fn ranks(values: &[i32]) -> Vec<f64> {
+    values
+        .iter()
+        .map(|value| {
+            let below = values.iter().filter(|other| *other < value).count();
+            let tied = values.iter().filter(|other| *other == value).count();
+
+            below as f64 + (tied as f64 + 1.0) / 2.0
+        })
+        .collect_vec()
+}
+
+/// Pearson correlation of two equally long series, or none if either doesn't vary
+fn pearson(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let (mean_x, mean_y) = (mean(xs.iter().copied()), mean(ys.iter().copied()));
+    let (cov, var_x, var_y) = xs
+        .iter()
+        .zip(ys)
+        .fold((0.0, 0.0, 0.0), |(cov, vx, vy), (x, y)| {
+            let (dx, dy) = (x - mean_x, y - mean_y);
+            (cov + dx * dy, vx + dx * dx, vy + dy * dy)
+        });
+
+    (var_x > 0.0 && var_y > 0.0).then(|| cov / (var_x * var_y).sqrt())
+}
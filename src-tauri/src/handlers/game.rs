@@ -1,48 +1,98 @@
-use std::{fs, path::Path};
+use std::{collections::HashMap, fs, path::Path};
 
-use futures::future::join_all;
 use itertools::Itertools;
-use rand::{Rng};
+use rand::Rng;
 use tap::Pipe;
 
 
 use crate::{
     conf::{LeagueConfig, LeagueConfigItem, TeamConfig, TeamConfigItem, TierConfigItem},
-    models::{game::GameState, league::League, schedule::Schedule, team::Team, tier::Tier},
-    paths::{get_leagues_config_path, get_team_config_path},
-    shared::pool::get_pool,
-    util::{PresentError},
+    ctx::OakenCtx,
+    error::OakenResult,
+    models::{
+        career::Career,
+        coach::{self, Coach},
+        finance::{Finances, PROMOTION_BONUS, RELEGATION_PENALTY},
+        game::GameState,
+        league::League,
+        matchup::{Matchday, Matchup, Outcome},
+        records::Record,
+        schedule::Schedule,
+        season::{Season, SeasonPhase},
+        standings::Standing,
+        team::{Team, TeamIdentity},
+        tier::Tier,
+    },
+    names::{self, NameGenerator},
+    shared::{emit::EmitMsg, undo::UndoHistory},
 };
 
+/// The year every new game starts in
+const FIRST_YEAR: i32 = 2023;
+
+/// How many teams are generated for each tier when there is no teams config
+const TEAMS_PER_TIER: usize = 16;
+
+/// How many teams swap places between adjacent tiers at the end of each season
+const PROMOTION_SPOTS: usize = 2;
+
+/// How many straight losses a coach can oversee before they are fired
+const FIRING_STREAK: i32 = 6;
+
 pub struct GameHandlers;
 
 impl GameHandlers {
-    pub async fn restart_game() {
-        let pool = get_pool();
+    pub async fn restart_game(ctx: &OakenCtx) -> OakenResult<()> {
+        let _advancing = ctx.advance_lock.lock().await;
+
+        Self::new_game(ctx).await
+    }
+
+    /// Wipes the save in use and starts a new game in it. The caller holds the advance lock
+    pub(crate) async fn new_game(ctx: &OakenCtx) -> OakenResult<()> {
+        let pool = ctx.pool()?;
+
+        ctx.emit(EmitMsg::GameWillRestart);
 
         pool.exec(
             "
+                    DELETE FROM inbox;
+                    DELETE FROM records;
+                    DELETE FROM team_runs;
+                    DELETE FROM coaching_spells;
+                    DELETE FROM coaches;
+                    DELETE FROM finances;
+                    DELETE FROM standings;
+                    DELETE FROM season_champions;
+                    DELETE FROM seasons;
                     DELETE FROM matchups;
                     DELETE FROM schedules;
                     DELETE FROM teams;
                     DELETE from tiers;
                     DELETE from leagues;",
-        );
+        )?;
+
+        // team ids don't survive a restart, so neither can the manager's job
+        Career::leave_job(ctx)?;
 
-        let league_config_path = get_leagues_config_path();
+        // nor can anything from before it be undone
+        UndoHistory::clear(ctx)?;
 
-        let LeagueConfig { leagues, tiers } = fs::read_to_string(league_config_path)
-            .present_err()
-            .unwrap()
-            .pipe(|s| toml::from_str(&s).present_err().unwrap());
+        let league_config_path = ctx.paths.get_leagues_config_path();
+
+        let LeagueConfig {
+            leagues,
+            tiers,
+            skill,
+        } = fs::read_to_string(league_config_path)?.pipe(|s| toml::from_str(&s))?;
 
         for LeagueConfigItem { name, abbr } in leagues {
-            League::create(name, abbr).await;
+            League::create(ctx, name, abbr).await?;
         }
 
         let mut rank = 1;
         for TierConfigItem { name, league } in tiers {
-            Tier::create(name, rank, League::get_by_name(league).await.id).await;
+            Tier::create(ctx, name, rank, League::get_by_name(ctx, league).await?.id).await?;
             rank += 1;
 
             if rank > 4 {
@@ -50,104 +100,311 @@ impl GameHandlers {
             }
         }
 
-        let team_config_path = get_team_config_path();
+        let team_config_path = ctx.paths.get_team_config_path();
 
-        if !team_config_path.exists() {
-            async fn generate_team_config(each: League) -> Vec<TeamConfigItem> {
-                let skill = rand::thread_rng().gen_range(1..=100);
-                let tiers = each.get_tiers().await;
+        let TeamConfig { teams } = if team_config_path.exists() {
+            fs::read_to_string(team_config_path)?.pipe(|s| toml::from_str(&s))?
+        } else {
+            let mut divisions = vec![];
 
-                tiers
+            for league in League::get_all(ctx).await? {
+                let tiers = league.get_tiers(ctx).await?;
+                divisions.push((league, tiers));
+            }
+
+            let config = {
+                let mut rng = ctx.rng();
+                let mut names = NameGenerator::default();
+
+                divisions
                     .iter()
-                    .flat_map(|tier| {
-                        (b'a'..(b'a' + 16))
-                            .map(|chr| (chr as char).to_string())
-                            .map(|chr| TeamConfigItem {
-                                name: format!(
-                                    "{}{} {}",
-                                    each.abbr,
-                                    tier.rank,
-                                    chr.clone().to_ascii_uppercase()
-                                ),
-                                tier: tier.name.clone(),
-                                league: each.name.clone(),
-                                skill,
-                            })
-                            .collect_vec()
+                    .flat_map(|(league, tiers)| tiers.iter().map(move |tier| (league, tier)))
+                    .flat_map(|division| (0..TEAMS_PER_TIER).map(move |_| division))
+                    .map(|(league, tier)| {
+                        let name = names.next(&mut *rng);
+                        let (primary_color, secondary_color) = names::colors(&mut *rng);
+
+                        TeamConfigItem {
+                            name: name.to_string(),
+                            tier: tier.name.clone(),
+                            league: league.name.clone(),
+                            skill: skill.sample(tier.rank, &mut *rng),
+                            capacity: rng.gen_range(20_000..=60_000) / tier.rank,
+                            abbr: Some(name.abbr),
+                            city: Some(name.city),
+                            founded: Some(rng.gen_range(1870..=1995)),
+                            primary_color: Some(primary_color),
+                            secondary_color: Some(secondary_color),
+                        }
                     })
                     .collect_vec()
+                    .pipe(|teams| TeamConfig { teams })
+            };
+
+            let contents: String = config.try_into()?;
+
+            // a game that isn't in a save, such as an in-memory one, keeps its world to itself
+            if ctx.paths.get_active_save().is_some() {
+                fs::write::<&Path, &str>(team_config_path.as_path(), &contents)?;
             }
 
-            let config = League::get_all()
-                .await
-                .into_iter()
-                .map(|each| generate_team_config(each))
-                .pipe(|it| join_all(it))
-                .await
-                .into_iter()
-                .flat_map(|vals| vals)
-                .collect_vec()
-                .pipe(|teams| TeamConfig { teams });
+            toml::from_str(&contents)?
+        };
+
+        for team in teams {
+            let identity = TeamIdentity::from(&team);
+            let league = League::get_by_name(ctx, team.league).await?;
+            let team = Team::create(
+                ctx,
+                team.name,
+                &identity,
+                team.skill,
+                team.capacity,
+                Tier::get_by_name(ctx, team.tier, league.id).await?.id,
+                league.id,
+            )
+            .await?;
+
+            Coach::generate(ctx).await?.hire(ctx, team.id, FIRST_YEAR, 1).await?;
+        }
 
-            fs::write::<&Path, String>(&team_config_path.as_path(), config.try_into().unwrap())
-                .unwrap()
+        for _ in 0..coach::POOL_SIZE {
+            Coach::generate(ctx).await?;
         }
 
-        if let Ok(TeamConfig { teams }) = fs::read_to_string(team_config_path)
-            .present_err()
-            .unwrap()
-            .pipe(|s| toml::from_str::<TeamConfig>(&s))
-        {
-            for team in teams {
-                let league = League::get_by_name(team.league).await;
-                Team::create(
-                    team.name,
-                    team.skill,
-                    Tier::get_by_name(team.tier, league.id).await.id,
-                    league.id,
-                )
-                .await;
+        Self::start_season(ctx, FIRST_YEAR).await?;
+        ctx.emit(EmitMsg::GameDidRestart { year: FIRST_YEAR });
+
+        Ok(())
+    }
+
+    /// Schedules every division for `year` and opens the books for the new season
+    async fn start_season(ctx: &OakenCtx, year: i32) -> OakenResult<()> {
+        let mut season = Season::create(ctx, year).await?;
+
+        for league in League::get_all(ctx).await? {
+            for tier in league.get_tiers(ctx).await? {
+                Schedule::create_round_robin(ctx, league.id, tier.id, &season).await?;
             }
+        }
+
+        season.update_end_wk(ctx).await?;
+
+        Standing::open_season(ctx, &year).await?;
+        GameState::set_year(ctx, year).await?;
+        GameState::set_week(ctx, 1).await?;
+        Finances::open_season(ctx, year).await
+    }
+
+    /// Pays out prize money, moves teams between tiers and rolls the game over into the next year.
+    /// Returns the records the season's champions broke
+    pub async fn end_season(ctx: &OakenCtx) -> OakenResult<Vec<Record>> {
+        let game = GameState::get(ctx).await?;
+        let tables = Standing::get_by_tier(ctx, &game.year).await?;
+
+        let career = Career::get(ctx).await?;
+        if let Some(table) = career.managed_team_id.and_then(|team_id| {
+            tables
+                .values()
+                .find(|table| table.iter().any(|standing| standing.team_id == team_id))
+        }) {
+            career.review_season(ctx, table, game.year, game.wk_no).await?;
+        }
+
+        let mut season = Season::get_by_year(ctx, &game.year).await?;
+        season.crown_champions(ctx, tables.values())?;
+        season.set_phase(ctx, SeasonPhase::Offseason).await?;
+
+        let champions = season.get_champions(ctx).await?;
+        let broken = Record::record_titles(ctx, &game.year, &game.wk_no, &champions).await?;
+
+        Finances::settle_season(ctx, &game.year, &tables).await?;
+        Self::promote_and_relegate(ctx, &game.year, &tables).await?;
+        Self::start_season(ctx, game.year + 1).await?;
+
+        Ok(broken)
+    }
+
+    async fn promote_and_relegate(
+        ctx: &OakenCtx, year: &i32, tables: &HashMap<i32, Vec<Standing>>,
+    ) -> OakenResult<()> {
+        for league in League::get_all(ctx).await? {
+            let tiers = league
+                .get_tiers(ctx)
+                .await?
+                .into_iter()
+                .sorted_by_key(|tier| tier.rank)
+                .collect_vec();
+
+            for (upper, lower) in tiers.iter().tuple_windows() {
+                let (Some(upper_table), Some(lower_table)) =
+                    (tables.get(&upper.id), tables.get(&lower.id))
+                else {
+                    continue;
+                };
+
+                let spots = PROMOTION_SPOTS
+                    .min(upper_table.len())
+                    .min(lower_table.len());
 
-            for league in League::get_all().await {
-                for tier in league.get_tiers().await {
-                    Schedule::create_round_robin(league.id, tier.id, 2023).await;
+                for standing in upper_table.iter().rev().take(spots) {
+                    Team::get(ctx, &standing.team_id).await?.set_tier(ctx, lower.id).await?;
+                    Finances::record_tier_change(ctx, standing.team_id, year, RELEGATION_PENALTY)?;
                 }
+
+                for standing in lower_table.iter().take(spots) {
+                    Team::get(ctx, &standing.team_id).await?.set_tier(ctx, upper.id).await?;
+                    Finances::record_tier_change(ctx, standing.team_id, year, PROMOTION_BONUS)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fires the coach of any team that lost this week and has now lost too many in a row under
+    /// them, bringing in a replacement from the pool for the following week. The managed team is
+    /// left alone, since its fate is down to the board
+    async fn review_coaches(
+        ctx: &OakenCtx, results: &[Matchup], managed_team_id: Option<i32>, year: i32, wk: i32,
+    ) -> OakenResult<()> {
+        let losers = results.iter().flat_map(|matchup| {
+            [matchup.home_team_id, matchup.away_team_id]
+                .into_iter()
+                .filter(|team_id| matchup.outcome_for(team_id) == Some(Outcome::Loss))
+        });
+
+        for team_id in losers.filter(|team_id| Some(*team_id) != managed_team_id) {
+            let Some(coach) = Coach::get_for_team(ctx, &team_id).await? else {
+                continue;
+            };
+
+            let games_in_charge = match coach.get_current_spell(ctx).await? {
+                Some(spell) if spell.start_year == year => wk - spell.start_wk + 1,
+                _ => wk,
+            };
+
+            let losses = Team::get(ctx, &team_id)
+                .await?
+                .get_streak_of(ctx, &year, Outcome::Loss)
+                .await?;
+
+            if losses.min(games_in_charge) < FIRING_STREAK {
+                continue;
             }
 
-            GameState::set_week(1).await;
+            let replacement = Coach::get_replacement(ctx).await?;
+            coach.fire(ctx, year, wk).await?;
+            replacement.hire(ctx, team_id, year, wk + 1).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Rewinds the game to just before the last week was played, including any season rollover
+    /// that came with it. Returns false if there is no week left to undo
+    pub async fn undo_week(ctx: &OakenCtx) -> OakenResult<bool> {
+        let _advancing = ctx.advance_lock.lock().await;
+
+        if !UndoHistory::pop(ctx).await? {
+            return Ok(false);
         }
+
+        let GameState { year, wk_no, .. } = GameState::get(ctx).await?;
+        ctx.emit(EmitMsg::WeekDidUndo { year, wk_no });
+
+        Ok(true)
     }
 
-    pub async fn next_week() {
-        let game = GameState::get().await;
+    pub async fn next_week(ctx: &OakenCtx) -> OakenResult<()> {
+        // a menu click and an rspc call can both ask to advance, so wait for any advance already
+        // under way to finish before looking at which week it is
+        let _advancing = ctx.advance_lock.lock().await;
+
+        let game = GameState::get(ctx).await?;
 
         // first, check that we even have a week to advance to
 
-        let final_wk = Schedule::get_all_by_year(&game.year)
-            .await
-            .into_iter()
-            .flat_map(|schedule| schedule.matchups)
-            .fold(0 as i32, |prev_max, matchup| matchup.wk_no.max(prev_max));
+        let season = Season::get_by_year(ctx, &game.year).await?;
 
-        if game.wk_no > final_wk {
-            return;
+        if season.end_wk == 0 {
+            return Ok(());
         }
 
-        // then compute all matches for this week
+        let snapshot = UndoHistory::take(ctx).await?;
+
+        // everything from here on is written in one go, so a failure part way through leaves the
+        // week unplayed rather than half played, with nothing for it to be undone to
+        let played = ctx
+            .pool()?
+            .transaction(Self::play_week(ctx, game, season))
+            .await;
+
+        let (results, broken) = match played {
+            Ok(played) => {
+                snapshot.keep(ctx)?;
+                played
+            }
+            Err(err) => {
+                snapshot.discard();
+                return Err(err);
+            }
+        };
+
+        let GameState { year, wk_no, .. } = GameState::get(ctx).await?;
+        ctx.emit(EmitMsg::WeekDidAdvance {
+            year,
+            wk_no,
+            results,
+        });
+
+        if !broken.is_empty() {
+            ctx.emit(EmitMsg::RecordsWereBroken { records: broken });
+        }
+
+        Ok(())
+    }
 
-        let matchups_for_this_wk = Schedule::get_all_by_year(&game.year)
-            .await
-            .into_iter()
-            .flat_map(|schedule| schedule.matchups)
-            .filter(|matchup| matchup.wk_no == game.wk_no)
-            .collect_vec();
+    /// Plays the week the game is on, returning the results of its matchups and the records they
+    /// broke
+    async fn play_week(
+        ctx: &OakenCtx, game: GameState, mut season: Season,
+    ) -> OakenResult<(Vec<Matchup>, Vec<Record>)> {
+        // once every week has been played, the next advance closes out the season
 
-        for matchup in matchups_for_this_wk {
-            matchup.compute_scores();
+        if game.wk_no > season.end_wk {
+            let broken = Self::end_season(ctx).await?;
+            return Ok((vec![], broken));
         }
 
-        // lastly, write the new week to the control table and refetch the new game state
-        GameState::set_week(game.wk_no + 1).await;
+        if season.phase == SeasonPhase::Preseason {
+            season.set_phase(ctx, SeasonPhase::Regular).await?;
+        }
+
+        // then compute all matches for this week
+
+        let matchups_for_this_wk = Matchup::get_for_week(ctx, &season.id, &game.wk_no).await?;
+
+        let matchday = Matchday::load(ctx, &game.year).await?;
+        let results = matchups_for_this_wk
+            .iter()
+            .map(|matchup| matchup.compute_scores(ctx, &matchday))
+            .collect::<OakenResult<Vec<_>>>()?;
+
+        Standing::record_week(ctx, &game.year, &game.wk_no).await?;
+        let broken = Record::record_week(ctx, &game.year, &game.wk_no, &results).await?;
+        Finances::settle_week(ctx, &game.year, &results, &matchday)?;
+
+        let career = Career::get(ctx).await?;
+        career
+            .review_week(ctx, &results, game.year, game.wk_no, season.end_wk)
+            .await?;
+
+        Self::review_coaches(ctx, &results, career.managed_team_id, game.year, game.wk_no).await?;
+
+        // lastly, write the new week to the control table
+        GameState::set_week(ctx, game.wk_no + 1).await?;
+
+        Ok((results, broken))
     }
 }
@@ -1,4 +1,6 @@
+pub mod batch;
 pub mod game;
+pub mod save;
 
 
 pub struct Handlers;
@@ -0,0 +1,503 @@
+use std::{fmt::Display, fs, io, path::Path};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use sqlx::{sqlite::SqliteConnectOptions, SqlitePool};
+
+use crate::{
+    ctx::OakenCtx,
+    error::{OakenError, OakenResult},
+    shared::{emit::EmitMsg, sql::SCHEMA_VER},
+};
+
+use super::game::GameHandlers;
+
+/// Metadata about a save kept next to its database
+static SAVE_MANIFEST: &str = "save.toml";
+/// Remembers which save to pick back up when the game is next opened
+static ACTIVE_SAVE_FILE: &str = "active";
+
+/// Describes the save bundled up in an exported archive
+static ARCHIVE_MANIFEST: &str = "manifest.toml";
+static ARCHIVE_DB: &str = "db.sqlite";
+/// World config files bundled alongside the database, when the save has them
+static ARCHIVE_CONFIGS: &[&str] = &["leagues.toml", "teams.toml"];
+
+#[derive(Serialize, Deserialize)]
+struct SaveManifest {
+    name: String,
+    /// When the save was started, in UTC. Saves carried over from before slots existed don't know
+    created_at: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArchiveManifest {
+    name: String,
+    created_at: Option<String>,
+    /// The schema the bundled database was on when it was exported
+    schema_ver: i32,
+}
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Io(io::Error),
+    InvalidManifest(toml::de::Error),
+    MissingEntry(&'static str),
+    /// The archive came from a newer version of the game than this one
+    UnsupportedSchema(i32),
+}
+
+impl Display for ArchiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read or write save archive: {err}"),
+            Self::InvalidManifest(err) => write!(f, "save archive has an invalid manifest: {err}"),
+            Self::MissingEntry(name) => write!(f, "save archive is missing {name}"),
+            Self::UnsupportedSchema(ver) => write!(
+                f,
+                "save archive is on schema version {ver}, but this version of Oaken only supports up to {SCHEMA_VER}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+impl From<io::Error> for ArchiveError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// How far a save has got, read straight from its database
+#[derive(sqlx::FromRow)]
+struct SaveProgress {
+    year: i32,
+    wk_no: i32,
+    managed_team: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveSlot {
+    pub id: i32,
+    pub name: String,
+    pub created_at: Option<String>,
+    pub year: i32,
+    pub wk_no: i32,
+    pub managed_team: Option<String>,
+    pub active: bool,
+}
+
+pub struct SaveHandlers;
+
+impl SaveHandlers {
+    fn next_id(ctx: &OakenCtx) -> i32 {
+        Self::get_ids(ctx).into_iter().max().unwrap_or(0) + 1
+    }
+
+    fn read_manifest(ctx: &OakenCtx, save_id: i32) -> Option<SaveManifest> {
+        fs::read_to_string(ctx.paths.get_save_dir(save_id).join(SAVE_MANIFEST))
+            .ok()
+            .and_then(|s| toml::from_str(&s).ok())
+    }
+
+    fn write_manifest(ctx: &OakenCtx, save_id: i32, manifest: &SaveManifest) -> OakenResult<()> {
+        fs::write(
+            ctx.paths.get_save_dir(save_id).join(SAVE_MANIFEST),
+            toml::to_string_pretty(manifest)?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Every save slot on disk, in the order they were created
+    fn get_ids(ctx: &OakenCtx) -> Vec<i32> {
+        let Ok(entries) = fs::read_dir(ctx.paths.get_saves_dir()) else {
+            return vec![];
+        };
+
+        entries
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<i32>().ok())
+            .filter(|save_id| ctx.paths.get_save_db_path(*save_id).exists())
+            .sorted()
+            .collect_vec()
+    }
+
+    async fn read_progress(pool: &SqlitePool) -> OakenResult<SaveProgress> {
+        let progress = sqlx::query_as(
+            r#"
+            SELECT ctrl.year, ctrl.wk_no, teams.name AS managed_team
+            FROM ctrl
+            LEFT JOIN teams ON teams.id = ctrl.managed_team_id;
+        "#,
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(progress)
+    }
+
+    fn ensure_exists(ctx: &OakenCtx, save_id: i32) -> OakenResult<()> {
+        if ctx.paths.get_save_db_path(save_id).exists() {
+            Ok(())
+        } else {
+            Err(OakenError::not_found("save", save_id))
+        }
+    }
+
+    async fn get_slot(ctx: &OakenCtx, save_id: i32) -> OakenResult<SaveSlot> {
+        Self::ensure_exists(ctx, save_id)?;
+
+        let active = ctx.paths.get_active_save() == Some(save_id);
+
+        let progress = if active {
+            Self::read_progress(&**ctx.pool()?).await?
+        } else {
+            // saves that aren't loaded are only peeked at, so they are never migrated from here
+            let opts = SqliteConnectOptions::new()
+                .filename(ctx.paths.get_save_db_path(save_id))
+                .read_only(true);
+
+            let pool = SqlitePool::connect_with(opts).await?;
+            let progress = Self::read_progress(&pool).await;
+            pool.close().await;
+
+            progress?
+        };
+
+        let SaveManifest { name, created_at } =
+            Self::read_manifest(ctx, save_id).unwrap_or_else(|| SaveManifest {
+                name: format!("Save {save_id}"),
+                created_at: None,
+            });
+
+        Ok(SaveSlot {
+            id: save_id,
+            name,
+            created_at,
+            year: progress.year,
+            wk_no: progress.wk_no,
+            managed_team: progress.managed_team,
+            active,
+        })
+    }
+
+    pub async fn list(ctx: &OakenCtx) -> OakenResult<Vec<SaveSlot>> {
+        let mut slots = vec![];
+
+        for save_id in Self::get_ids(ctx) {
+            slots.push(Self::get_slot(ctx, save_id).await?);
+        }
+
+        Ok(slots)
+    }
+
+    /// Switches the game over to the database and world config of `save_id`
+    pub async fn load(ctx: &OakenCtx, save_id: i32) -> OakenResult<SaveSlot> {
+        let _switching = ctx.advance_lock.lock().await;
+
+        // opening the pool would otherwise create an empty database for a save that isn't there
+        Self::ensure_exists(ctx, save_id)?;
+
+        Self::open(ctx, save_id).await
+    }
+
+    /// Opens the database of `save_id`, creating it if it's new, and makes it the save in use. The
+    /// caller holds the advance lock, so the pool isn't swapped out from under a week being played
+    async fn open(ctx: &OakenCtx, save_id: i32) -> OakenResult<SaveSlot> {
+        ctx.init_pool(&ctx.paths.get_save_db_path(save_id)).await?;
+        ctx.paths.set_active_save(Some(save_id));
+
+        fs::write(
+            ctx.paths.get_saves_dir().join(ACTIVE_SAVE_FILE),
+            save_id.to_string(),
+        )?;
+
+        ctx.emit(EmitMsg::SaveDidLoad { save_id });
+
+        Self::get_slot(ctx, save_id).await
+    }
+
+    /// Sets up a new slot with its own copy of the world config, loads it and starts a new game
+    /// in it
+    pub async fn create(ctx: &OakenCtx, name: String) -> OakenResult<SaveSlot> {
+        let _switching = ctx.advance_lock.lock().await;
+
+        Self::start(ctx, name).await
+    }
+
+    /// Does the work of [`Self::create`] for a caller that holds the advance lock
+    async fn start(ctx: &OakenCtx, name: String) -> OakenResult<SaveSlot> {
+        let save_id = Self::next_id(ctx);
+
+        fs::create_dir_all(ctx.paths.get_save_dir(save_id))?;
+        Self::copy_world_config(ctx, save_id)?;
+        Self::open(ctx, save_id).await?;
+
+        let created_at: String = sqlx::query_scalar("SELECT datetime('now');")
+            .fetch_one(&**ctx.pool()?)
+            .await?;
+
+        Self::write_manifest(
+            ctx,
+            save_id,
+            &SaveManifest {
+                name,
+                created_at: Some(created_at),
+            },
+        )?;
+
+        GameHandlers::new_game(ctx).await?;
+
+        Self::get_slot(ctx, save_id).await
+    }
+
+    /// Gives a save its own copy of the world config, so later edits to the defaults only affect
+    /// new saves
+    fn copy_world_config(ctx: &OakenCtx, save_id: i32) -> OakenResult<()> {
+        let copies = [
+            (
+                ctx.paths.get_default_leagues_config_path(),
+                ctx.paths.get_save_leagues_config_path(save_id),
+            ),
+            (
+                ctx.paths.get_default_team_config_path(),
+                ctx.paths.get_save_team_config_path(save_id),
+            ),
+        ];
+
+        for (from, to) in copies.iter().filter(|(from, _)| from.exists()) {
+            fs::copy(from, to)?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn rename(ctx: &OakenCtx, save_id: i32, name: String) -> OakenResult<SaveSlot> {
+        Self::ensure_exists(ctx, save_id)?;
+
+        let manifest = Self::read_manifest(ctx, save_id)
+            .map(|manifest| SaveManifest { name: name.clone(), ..manifest })
+            .unwrap_or_else(|| SaveManifest {
+                name,
+                created_at: None,
+            });
+
+        Self::write_manifest(ctx, save_id, &manifest)?;
+
+        Self::get_slot(ctx, save_id).await
+    }
+
+    /// Removes a save for good. Deleting the save in use moves the game onto another one
+    pub async fn delete(ctx: &OakenCtx, save_id: i32) -> OakenResult<Vec<SaveSlot>> {
+        let _switching = ctx.advance_lock.lock().await;
+
+        Self::ensure_exists(ctx, save_id)?;
+
+        let was_active = ctx.paths.get_active_save() == Some(save_id);
+
+        if was_active {
+            ctx.close_pool().await;
+            ctx.paths.set_active_save(None);
+        }
+
+        fs::remove_dir_all(ctx.paths.get_save_dir(save_id))?;
+
+        if was_active {
+            Self::pick_up(ctx).await?;
+        }
+
+        Self::list(ctx).await
+    }
+
+    /// Loads whichever save was in use last time the game was open, creating one if there are
+    /// none yet
+    pub async fn resume(ctx: &OakenCtx) -> OakenResult<()> {
+        let _switching = ctx.advance_lock.lock().await;
+
+        Self::pick_up(ctx).await
+    }
+
+    /// Does the work of [`Self::resume`] for a caller that holds the advance lock
+    async fn pick_up(ctx: &OakenCtx) -> OakenResult<()> {
+        Self::adopt_legacy_save(ctx)?;
+
+        let ids = Self::get_ids(ctx);
+
+        let last_active = fs::read_to_string(ctx.paths.get_saves_dir().join(ACTIVE_SAVE_FILE))
+            .ok()
+            .and_then(|s| s.trim().parse::<i32>().ok())
+            .filter(|save_id| ids.contains(save_id));
+
+        match last_active.or(ids.last().copied()) {
+            Some(save_id) => Self::open(ctx, save_id).await?,
+            None => Self::start(ctx, "Save 1".to_owned()).await?,
+        };
+
+        Ok(())
+    }
+
+    /// Moves the single database from before save slots existed into a slot of its own, so that
+    /// the game in it carries on as the first save
+    fn adopt_legacy_save(ctx: &OakenCtx) -> OakenResult<()> {
+        let legacy_db = ctx.paths.get_local_data_dir().join("db.sqlite");
+
+        if !legacy_db.exists() || !Self::get_ids(ctx).is_empty() {
+            return Ok(());
+        }
+
+        let save_id = 1;
+
+        fs::create_dir_all(ctx.paths.get_save_dir(save_id))?;
+
+        // a game that wasn't shut down cleanly may still have writes sitting in its journal
+        for suffix in ["", "-wal", "-shm"] {
+            let from = legacy_db.with_file_name(format!("db.sqlite{suffix}"));
+            let to = ctx
+                .paths
+                .get_save_db_path(save_id)
+                .with_file_name(format!("db.sqlite{suffix}"));
+
+            if from.exists() {
+                fs::rename(from, to)?;
+            }
+        }
+
+        Self::copy_world_config(ctx, save_id)?;
+
+        Self::write_manifest(
+            ctx,
+            save_id,
+            &SaveManifest {
+                name: "Save 1".to_owned(),
+                created_at: None,
+            },
+        )
+    }
+
+    /// Bundles the database and world config of the game in use into a single archive at `path`
+    pub async fn export(ctx: &OakenCtx, path: &Path) -> OakenResult<()> {
+        let (SaveManifest { name, created_at }, snapshot) = match ctx.paths.get_active_save() {
+            Some(save_id) => (
+                Self::read_manifest(ctx, save_id).unwrap_or_else(|| SaveManifest {
+                    name: format!("Save {save_id}"),
+                    created_at: None,
+                }),
+                ctx.paths.get_save_dir(save_id).join("export.sqlite"),
+            ),
+            // a game run straight off a database goes out with the world config it was started from
+            None => (
+                SaveManifest {
+                    name: "Exported game".to_owned(),
+                    created_at: None,
+                },
+                std::env::temp_dir().join(format!("oaken-export-{}.sqlite", std::process::id())),
+            ),
+        };
+
+        // copy the database out first so the archive doesn't catch it halfway through a write
+        if snapshot.exists() {
+            fs::remove_file(&snapshot)?;
+        }
+
+        sqlx::query("VACUUM INTO $1;")
+            .bind(snapshot.to_string_lossy())
+            .execute(&**ctx.pool()?)
+            .await?;
+
+        let manifest = toml::to_string_pretty(&ArchiveManifest {
+            name,
+            created_at,
+            schema_ver: SCHEMA_VER,
+        })?;
+
+        let mut archive = tar::Builder::new(GzEncoder::new(
+            fs::File::create(path)?,
+            Compression::default(),
+        ));
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        archive.append_data(&mut header, ARCHIVE_MANIFEST, manifest.as_bytes())?;
+
+        archive.append_path_with_name(&snapshot, ARCHIVE_DB)?;
+
+        for name in ARCHIVE_CONFIGS {
+            let path = ctx.paths.get_config_dir().join(name);
+
+            if path.exists() {
+                archive.append_path_with_name(path, name)?;
+            }
+        }
+
+        archive.into_inner()?.finish()?;
+        fs::remove_file(&snapshot)?;
+
+        Ok(())
+    }
+
+    /// Unpacks an exported archive into a new save and loads it. Archives from older versions of
+    /// the game are migrated as they load
+    pub async fn import(ctx: &OakenCtx, path: &Path) -> OakenResult<SaveSlot> {
+        let _switching = ctx.advance_lock.lock().await;
+
+        let save_id = Self::next_id(ctx);
+        let save_dir = ctx.paths.get_save_dir(save_id);
+
+        fs::create_dir_all(&save_dir)?;
+
+        let imported = async {
+            let ArchiveManifest {
+                name, created_at, ..
+            } = Self::unpack(path, &save_dir)?;
+
+            Self::write_manifest(ctx, save_id, &SaveManifest { name, created_at })?;
+            Self::open(ctx, save_id).await
+        }
+        .await;
+
+        // an archive that fails before its save is in use, such as one whose database can't be
+        // opened or migrated, leaves no slot behind
+        if imported.is_err() && ctx.paths.get_active_save() != Some(save_id) {
+            fs::remove_dir_all(&save_dir)?;
+        }
+
+        imported
+    }
+
+    fn unpack(path: &Path, save_dir: &Path) -> Result<ArchiveManifest, ArchiveError> {
+        let mut archive = tar::Archive::new(GzDecoder::new(fs::File::open(path)?));
+        let mut manifest = None;
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let entry_path = entry.path()?.to_string_lossy().into_owned();
+
+            if entry_path == ARCHIVE_MANIFEST {
+                let contents = io::read_to_string(&mut entry)?;
+                manifest = Some(toml::from_str::<ArchiveManifest>(&contents));
+            } else if entry_path == ARCHIVE_DB || ARCHIVE_CONFIGS.contains(&entry_path.as_str()) {
+                entry.unpack(save_dir.join(&entry_path))?;
+            }
+        }
+
+        let manifest = manifest
+            .ok_or(ArchiveError::MissingEntry(ARCHIVE_MANIFEST))?
+            .map_err(ArchiveError::InvalidManifest)?;
+
+        if manifest.schema_ver > SCHEMA_VER {
+            return Err(ArchiveError::UnsupportedSchema(manifest.schema_ver));
+        }
+
+        if !save_dir.join(ARCHIVE_DB).exists() {
+            return Err(ArchiveError::MissingEntry(ARCHIVE_DB));
+        }
+
+        Ok(manifest)
+    }
+}
@@ -1,7 +1,10 @@
 pub mod conf;
+pub mod ctx;
+pub mod error;
 pub mod handlers;
 pub mod menu;
 pub mod models;
+pub mod names;
 pub mod paths;
 pub mod util;
 
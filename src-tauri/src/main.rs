@@ -3,40 +3,46 @@
 
 
 use oaken::{
-    conf::{LeagueConfig},
-    handlers::game::GameHandlers,
+    ctx::OakenCtx,
+    handlers::{game::GameHandlers, save::SaveHandlers},
     inline_async,
     menu::build_menu,
     models::{
+        career::{Career, InboxMessage, ManagerPlan},
+        coach::Coach,
+        finance::Finances,
         game::GameState,
-        league::{LeagueInfo},
-        matchup::Matchup,
+        head_to_head::HeadToHead,
+        league::LeagueInfo,
+        matchup::{Matchup, MatchupSearch},
+        records::{Record, RecordScope},
         schedule::Schedule,
+        season::Season,
         standings::Standing,
         team::{Team, TeamInfo},
+        team_history::{TeamHistory, TeamSeason},
     },
-    paths::{get_leagues_config_path},
-    shared::{
-        emit::EmitMsg,
-        pool::{get_pool, init_pool},
-        APP_HNDL,
-    },
-    sql_args,
+    paths::Paths,
+    util::PresentError,
 };
 
-
-
+use axum::{
+    extract::State,
+    http::{header, Request, StatusCode},
+    middleware::{self, Next},
+    response::Response,
+};
+use rand::{distributions::Alphanumeric, Rng};
 use rspc::Router;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use specta::{ts::ExportConfiguration, Type};
-use std::{
-    fs,
-    path::Path,
-    sync::{Arc},
-};
+use specta::Type;
+use std::{net::SocketAddr, path::Path, sync::Arc};
+
+use tauri::api::dialog::FileDialogBuilder;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 
-use tauri::{Manager};
+/// File extension given to exported saves
+const SAVE_EXTENSION: &str = "oaken";
 
 #[derive(Serialize, Type)]
 struct AppState {
@@ -50,68 +56,370 @@ impl AppState {
     }
 }
 
-struct AppCtx {}
+#[derive(Clone)]
+struct AppCtx {
+    oaken: Arc<OakenCtx>,
+}
+
+#[derive(Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+struct RenameSaveArgs {
+    save_id: i32,
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+struct GetHeadToHeadArgs {
+    team_a_id: i32,
+    team_b_id: i32,
+}
+
+#[derive(Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+struct GetTeamSeasonArgs {
+    team_id: i32,
+    year: i32,
+}
 
 #[derive(Serialize, Deserialize, Type)]
 #[serde(rename_all = "camelCase")]
-struct GetMatchupsByWeekArgs {
+struct GetFinancesArgs {
+    team_id: i32,
     year: i32,
-    wk_no: i32,
+}
+
+/// Prints `msg` and quits, for a launch that can't go ahead
+fn exit_with(msg: impl std::fmt::Display) -> ! {
+    eprintln!("{msg}");
+    std::process::exit(1)
+}
+
+/// The address given with `--serve`, if the router should be served outside the webview too.
+/// Only addresses on this machine are allowed, since anything served can rewrite the save
+fn serve_addr() -> Option<SocketAddr> {
+    let addr = std::env::args().skip_while(|arg| arg != "--serve").nth(1)?;
+
+    match addr.parse::<SocketAddr>() {
+        Ok(addr) if addr.ip().is_loopback() => Some(addr),
+        Ok(_) => exit_with(format!(
+            "--serve only listens on this machine, so expects a loopback address such as \
+             127.0.0.1:4000, got '{addr}'"
+        )),
+        Err(_) => exit_with(format!(
+            "--serve expects an address such as 127.0.0.1:4000, got '{addr}'"
+        )),
+    }
+}
+
+/// Whether `origin` is a page served from this machine, or the app's own window
+fn is_local_origin(origin: &str) -> bool {
+    ["http://localhost", "http://127.0.0.1", "tauri://localhost"]
+        .iter()
+        .any(|local| {
+            origin
+                .strip_prefix(local)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(':'))
+        })
+}
+
+/// Turns away requests that don't carry `token`, either as a bearer token or as a `token` query
+/// parameter for WebSockets, which browsers can't set headers on. Requests made from a web page
+/// are turned away too unless the page is on this machine, since CORS alone doesn't cover
+/// WebSocket upgrades or simple POSTs
+async fn authorize<B>(
+    State(token): State<Arc<str>>, req: Request<B>, next: Next<B>,
+) -> Result<Response, StatusCode> {
+    let origin = req.headers().get(header::ORIGIN);
+
+    if origin.is_some_and(|origin| !is_local_origin(origin.to_str().unwrap_or_default())) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let bearer = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok()?.strip_prefix("Bearer "));
+    let param = req.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+    });
+
+    // compared in full whatever the input, so the time taken gives nothing away
+    let matches = |given: &str| {
+        given.len() == token.len()
+            && given
+                .bytes()
+                .zip(token.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    };
+
+    if bearer.or(param).is_some_and(matches) {
+        Ok(next.run(req).await)
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// Serves `router` over HTTP at `/rspc/<procedure>` and over WebSocket at `/rspc/ws`, so scripts,
+/// notebooks and other windows can drive the same game as the app. Every request must carry the
+/// token printed at launch, which changes each time the app is opened
+fn serve(router: Arc<Router<AppCtx>>, ctx: Arc<OakenCtx>, addr: SocketAddr) {
+    // bind up front, so a taken port stops the app before it gets going
+    let server = axum::Server::try_bind(&addr)
+        .unwrap_or_else(|err| exit_with(format!("failed to serve the router on {addr}: {err}")));
+
+    let token: Arc<str> = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect::<String>()
+        .into();
+
+    // answers preflights from local pages, which never carry the token themselves
+    let cors = CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(|origin, _| {
+            is_local_origin(origin.to_str().unwrap_or_default())
+        }))
+        .allow_methods(Any)
+        .allow_headers(Any);
+
+    let app = axum::Router::new()
+        .nest(
+            "/rspc",
+            router
+                .endpoint(move || AppCtx { oaken: ctx.clone() })
+                .axum(),
+        )
+        .layer(middleware::from_fn_with_state(token.clone(), authorize))
+        .layer(cors);
+
+    tauri::async_runtime::spawn(async move {
+        if let Err(err) = server.serve(app.into_make_service()).await {
+            eprintln!("stopped serving the router on {addr}: {err}");
+        }
+    });
+
+    println!("Serving the router on http://{addr}/rspc with token {token}");
 }
 
 fn router() -> Arc<Router<AppCtx>> {
     let router: Router<AppCtx> = Router::new()
         .config(rspc::Config::new().export_ts_bindings("../src/bindings.d.ts"))
         .query("getTeamInfos", |t| {
-            t(|_ctx: AppCtx, _: ()| async { TeamInfo::get_all().await })
+            t(|ctx: AppCtx, _: ()| async move {
+                TeamInfo::get_all(&ctx.oaken)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
         })
         .query("getTeams", |t| {
-            t(|_ctx: AppCtx, _: ()| async { Team::get_all().await })
+            t(|ctx: AppCtx, _: ()| async move {
+                Team::get_all(&ctx.oaken)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
         })
         .query("getGameState", |t| {
-            t(|_ctx, _: ()| async { GameState::get().await })
+            t(|ctx: AppCtx, _: ()| async move {
+                GameState::get(&ctx.oaken)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
         })
-        .query("getAllSchedules", |t| {
-            t(|_ctx, _: ()| async { Schedule::get_all().await })
+        .query("getSeasons", |t| {
+            t(|ctx: AppCtx, _: ()| async move {
+                Season::get_all(&ctx.oaken)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .query("getSeasonChampions", |t| {
+            t(|ctx: AppCtx, year: i32| async move {
+                let season = Season::get_by_year(&ctx.oaken, &year).await?;
+
+                season.get_champions(&ctx.oaken).await.map_err(rspc::Error::from)
+            })
         })
         .query("getSchedulesByYear", |t| {
-            t(|_ctx, year: i32| async move { Schedule::get_all_by_year(&year).await })
+            t(|ctx: AppCtx, year: i32| async move {
+                Schedule::get_all_by_year(&ctx.oaken, &year)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .query("searchMatchups", |t| {
+            t(|ctx: AppCtx, search: MatchupSearch| async move {
+                Matchup::search(&ctx.oaken, &search)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
         })
-        .query("getTeamMatchups", |t| {
-            t(|_ctx, team_id: i32| async move { Matchup::get_with_teamid(&team_id).await })
+        .query("getHeadToHead", |t| {
+            t(|ctx: AppCtx, args: GetHeadToHeadArgs| async move {
+                HeadToHead::get(&ctx.oaken, args.team_a_id, args.team_b_id)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .query("getTeamSeason", |t| {
+            t(|ctx: AppCtx, args: GetTeamSeasonArgs| async move {
+                TeamSeason::get(&ctx.oaken, args.team_id, args.year)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .query("getTeamHistory", |t| {
+            t(|ctx: AppCtx, team_id: i32| async move {
+                TeamHistory::get(&ctx.oaken, team_id)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .query("getRecords", |t| {
+            t(|ctx: AppCtx, scope: RecordScope| async move {
+                Record::get(&ctx.oaken, &scope)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
         })
         .query("getStandings", |t| {
-            t(|_ctx, year: i32| async move { Standing::get(&year).await })
+            t(|ctx: AppCtx, year: i32| async move {
+                Standing::get(&ctx.oaken, &year)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .query("getCareer", |t| {
+            t(|ctx: AppCtx, _: ()| async move {
+                Career::get(&ctx.oaken)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .query("getManagerInbox", |t| {
+            t(|ctx: AppCtx, _: ()| async move {
+                InboxMessage::get_all(&ctx.oaken)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .query("getCoach", |t| {
+            t(|ctx: AppCtx, team_id: i32| async move {
+                Coach::get_for_team(&ctx.oaken, &team_id)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .query("getCoachingHistory", |t| {
+            t(|ctx: AppCtx, team_id: i32| async move {
+                Coach::get_history(&ctx.oaken, &team_id)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .query("getFinances", |t| {
+            t(|ctx: AppCtx, args: GetFinancesArgs| async move {
+                Finances::get(&ctx.oaken, &args.team_id, &args.year)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .query("listSaves", |t| {
+            t(|ctx: AppCtx, _: ()| async move {
+                SaveHandlers::list(&ctx.oaken)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
         })
         .query("getLeagueInfos", |t| {
-            t(|_ctx, _: ()| async { LeagueInfo::get_all().await })
+            t(|ctx: AppCtx, _: ()| async move {
+                LeagueInfo::get_all(&ctx.oaken)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
         })
-        .query("getMatchupsByWeek", |t| {
-            t(|_ctx, args: GetMatchupsByWeekArgs| async move {
-                let matchups: Vec<Matchup> = get_pool()
-                    .query_with(
-                        r#"
-                                SELECT * FROM matchups
-                                INNER JOIN schedules ON matchups.schedule_id = schedules.id
-                                WHERE schedules.year = $1 AND matchups.wk_no = $2;
-                            "#,
-                        sql_args![args.year, args.wk_no],
-                    )
-                    .await;
-
-                return matchups;
+        .mutation("createSave", |t| {
+            t(|ctx: AppCtx, name: String| async move {
+                SaveHandlers::create(&ctx.oaken, name)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .mutation("loadSave", |t| {
+            t(|ctx: AppCtx, save_id: i32| async move {
+                SaveHandlers::load(&ctx.oaken, save_id)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .mutation("renameSave", |t| {
+            t(|ctx: AppCtx, args: RenameSaveArgs| async move {
+                SaveHandlers::rename(&ctx.oaken, args.save_id, args.name)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .mutation("deleteSave", |t| {
+            t(|ctx: AppCtx, save_id: i32| async move {
+                SaveHandlers::delete(&ctx.oaken, save_id)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .mutation("undoWeek", |t| {
+            t(|ctx: AppCtx, _: ()| async move {
+                GameHandlers::undo_week(&ctx.oaken)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .mutation("exportSave", |t| {
+            t(|ctx: AppCtx, path: String| async move {
+                SaveHandlers::export(&ctx.oaken, Path::new(&path))
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .mutation("importSave", |t| {
+            t(|ctx: AppCtx, path: String| async move {
+                SaveHandlers::import(&ctx.oaken, Path::new(&path))
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .mutation("rebuildStandings", |t| {
+            t(|ctx: AppCtx, year: i32| async move {
+                Standing::rebuild(&ctx.oaken, &year)
+                    .await
+                    .map_err(rspc::Error::from)
+            })
+        })
+        .mutation("setManagedTeam", |t| {
+            t(|ctx: AppCtx, team_id: i32| async move {
+                let GameState { year, wk_no, .. } = GameState::get(&ctx.oaken).await?;
+                Ok::<_, rspc::Error>(Career::take_job(&ctx.oaken, team_id, year, wk_no).await?)
+            })
+        })
+        .mutation("setManagerPlan", |t| {
+            t(|ctx: AppCtx, plan: ManagerPlan| async move {
+                Career::set_plan(&ctx.oaken, plan)
+                    .await
+                    .map_err(rspc::Error::from)
             })
         })
         .mutation("advanceWeek", |t| {
-            t(|_ctx, _: ()| async {
-                GameHandlers::next_week().await;
-                APP_HNDL
-                    .get()
-                    .unwrap()
-                    .emit_all("week_did_advance", json!({}))
-                    .unwrap();
+            t(|ctx: AppCtx, _: ()| async move {
+                GameHandlers::next_week(&ctx.oaken)
+                    .await
+                    .map_err(rspc::Error::from)
             })
         })
+        .subscription("gameEvents", |t| {
+            t(|ctx: AppCtx, _: ()| ctx.oaken.events())
+        })
         .build();
 
     router.arced()
@@ -123,56 +431,89 @@ async fn main() {
 
     tauri::async_runtime::set(tokio::runtime::Handle::current());
 
-    tauri::Builder::default()
-        .setup(|app| {
-            let ts_str = specta::ts::export::<EmitMsg>(&ExportConfiguration::new())
-                .expect("failed to export EmitMsg typescript bindings");
+    let ctx = Arc::new(OakenCtx::new(Paths::new(
+        tauri::api::path::app_config_dir(context.config()).expect("no app config dir"),
+        tauri::api::path::app_local_data_dir(context.config()).expect("no app local data dir"),
+    )));
 
-            fs::write("../src/lib/msg_bindings.d.ts", format!("// This file was generated by Oaken. Do not edit this file manually!\n\n{ts_str}"))
-                .expect("failed to write ts_str to disk");
+    let router = router();
 
-            inline_async! {
-                init_pool(app).await;
-            }
+    tauri::Builder::default()
+        .setup({
+            let ctx = ctx.clone();
+            let router = router.clone();
 
-            APP_HNDL.set(app.handle()).unwrap();
+            move |_| {
+                ctx.paths
+                    .ensure_default_leagues_config()
+                    .expect("failed to write the default leagues config");
 
-            let league_config_path = get_leagues_config_path();
+                inline_async! {
+                    SaveHandlers::resume(&ctx).await.present_err().expect("failed to load a save");
+                }
 
-            if !league_config_path.exists() {
-                fs::write::<&Path, String>(
-                    &league_config_path.as_path(),
-                    LeagueConfig::default().try_into().unwrap(),
-                )
-                .unwrap()
-            }
+                // only once there's a save for requests to read from
+                if let Some(addr) = serve_addr() {
+                    serve(router, ctx, addr);
+                }
 
-            Ok(())
+                Ok(())
+            }
         })
-        .on_menu_event(|evt| {
-            let window = evt.window().clone();
+        .on_menu_event({
+            let ctx = ctx.clone();
 
-            match evt.menu_item_id() {
-                "restart_game" => {
-                    window.emit("game_will_restart", json!({})).unwrap();
+            move |evt| {
+                match evt.menu_item_id() {
+                    "restart_game" => {
+                        let ctx = ctx.clone();
+                        tauri::async_runtime::spawn(async move {
+                            let _ = GameHandlers::restart_game(&ctx).await.present_err();
+                        });
+                    }
+                    "next_week" => {
+                        inline_async! {
+                            GameHandlers::next_week(&ctx).await.present_err().ok();
+                        }
+                    }
+                    "undo_week" => {
+                        inline_async! {
+                            GameHandlers::undo_week(&ctx).await.present_err().ok();
+                        }
+                    }
+                    "export_save" => {
+                        let ctx = ctx.clone();
 
-                    tauri::async_runtime::spawn(async move {
-                        GameHandlers::restart_game().await;
-                        window.emit("game_did_restart", json!({})).unwrap();
-                    });
-                }
-                "next_week" => {
-                    inline_async! {
-                        GameHandlers::next_week().await;
+                        FileDialogBuilder::new()
+                            .add_filter("Oaken Save", &[SAVE_EXTENSION])
+                            .set_file_name(&format!("save.{SAVE_EXTENSION}"))
+                            .save_file(|path| {
+                                let Some(path) = path else { return };
+
+                                tauri::async_runtime::spawn(async move {
+                                    let _ = SaveHandlers::export(&ctx, &path).await.present_err();
+                                });
+                            });
                     }
+                    "import_save" => {
+                        let ctx = ctx.clone();
+
+                        FileDialogBuilder::new()
+                            .add_filter("Oaken Save", &[SAVE_EXTENSION])
+                            .pick_file(move |path| {
+                                let Some(path) = path else { return };
 
-                    window.emit("week_did_advance", json!({})).unwrap();
+                                tauri::async_runtime::spawn(async move {
+                                    let _ = SaveHandlers::import(&ctx, &path).await.present_err();
+                                });
+                            });
+                    }
+                    &_ => todo!(),
                 }
-                &_ => todo!(),
             }
         })
-        .plugin(rspc::integrations::tauri::plugin(router(), move || {
-            AppCtx {}
+        .plugin(rspc::integrations::tauri::plugin(router, move || AppCtx {
+            oaken: ctx.clone(),
         }))
         .menu(build_menu(&context))
         .run(context)
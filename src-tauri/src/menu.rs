@@ -29,6 +29,12 @@ pub fn build_menu(ctx: &Context<EmbeddedAssets>) -> Menu {
 
         menu = menu.add_item(CustomMenuItem::new("restart_game", "Restart"));
         menu = menu.add_item(CustomMenuItem::new("next_week", "Next Week"));
+        menu = menu.add_item(CustomMenuItem::new("undo_week", "Undo Week"));
+
+        menu = menu.add_native_item(MenuItem::Separator);
+
+        menu = menu.add_item(CustomMenuItem::new("import_save", "Import Save..."));
+        menu = menu.add_item(CustomMenuItem::new("export_save", "Export Save..."));
 
         menu = menu.add_native_item(MenuItem::Separator);
 
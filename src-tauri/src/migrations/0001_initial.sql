@@ -59,4 +59,4 @@ CREATE TABLE schedules (
 
 --
 
-INSERT INTO ctrl (schema_ver, year, wk_no) VALUES ($1, 2023, 1);
\ No newline at end of file
+INSERT INTO ctrl (schema_ver, year, wk_no) VALUES (1, 2023, 1);
\ No newline at end of file
@@ -0,0 +1,41 @@
+-- finances table
+CREATE TABLE finances (
+    team_id INTEGER NOT NULL,
+    year INTEGER NOT NULL,
+    opening_balance INTEGER NOT NULL,
+    budget INTEGER NOT NULL,
+    gate_revenue INTEGER NOT NULL DEFAULT 0,
+    prize_money INTEGER NOT NULL DEFAULT 0,
+    tier_change INTEGER NOT NULL DEFAULT 0,
+    wages INTEGER NOT NULL DEFAULT 0,
+    balance INTEGER NOT NULL,
+
+    FOREIGN KEY (team_id) REFERENCES teams (id),
+    CONSTRAINT unique_year_per_team UNIQUE (team_id, year)
+);
+
+-- open the books for the season under way the same way a new game does, with each tier starting
+-- out on 60% of the one above it. Weeks played before the upgrade go unsettled
+WITH RECURSIVE scales (rank, scale) AS (
+    SELECT 1, 1.0
+    UNION ALL
+    SELECT rank + 1, scale * 0.6 FROM scales WHERE rank < (SELECT MAX(rank) FROM tiers)
+),
+openings AS (
+    SELECT
+        teams.id AS team_id,
+        CAST(10000000 * scales.scale AS INTEGER) AS balance,
+        CAST(250000 * scales.scale AS INTEGER) * 10 AS expected_gate
+    FROM teams
+    INNER JOIN tiers ON tiers.id = teams.tier_id
+    INNER JOIN scales ON scales.rank = tiers.rank
+)
+INSERT INTO finances (team_id, year, opening_balance, budget, balance)
+SELECT
+    openings.team_id,
+    ctrl.year,
+    openings.balance,
+    openings.expected_gate + openings.balance / 4,
+    openings.balance
+FROM openings
+CROSS JOIN ctrl;
@@ -0,0 +1,11 @@
+-- how many each team's stadium holds. Teams from before it was tracked get the middle of the
+-- range a new game picks from for their tier
+ALTER TABLE teams ADD COLUMN capacity INTEGER NOT NULL DEFAULT 0;
+
+UPDATE teams
+SET capacity = 40000 / tiers.rank
+FROM tiers
+WHERE tiers.id = teams.tier_id;
+
+-- how many came to each game, once it has been played
+ALTER TABLE matchups ADD COLUMN attendance INTEGER;
@@ -0,0 +1,33 @@
+-- coaches tables
+CREATE TABLE coaches (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    rating INTEGER NOT NULL,
+    team_id INTEGER,
+
+    FOREIGN KEY (team_id) REFERENCES teams (id)
+);
+
+CREATE TABLE coaching_spells (
+    coach_id INTEGER NOT NULL,
+    team_id INTEGER NOT NULL,
+    start_year INTEGER NOT NULL,
+    start_wk INTEGER NOT NULL,
+    end_year INTEGER,
+    end_wk INTEGER,
+
+    FOREIGN KEY (coach_id) REFERENCES coaches (id),
+    FOREIGN KEY (team_id) REFERENCES teams (id)
+);
+
+-- every team starts out under a caretaker from the week the save is upgraded. Their middling
+-- rating neither adds to nor takes from the team's strength, so results play out as before until
+-- a run of losses brings in a proper coach
+INSERT INTO coaches (name, rating, team_id)
+SELECT name || ' Caretaker', 50, id
+FROM teams;
+
+INSERT INTO coaching_spells (coach_id, team_id, start_year, start_wk)
+SELECT coaches.id, coaches.team_id, ctrl.year, ctrl.wk_no
+FROM coaches
+CROSS JOIN ctrl;
@@ -0,0 +1,13 @@
+-- everything that makes a team recognisable beyond its name
+ALTER TABLE teams ADD COLUMN abbr TEXT NOT NULL DEFAULT '';
+ALTER TABLE teams ADD COLUMN city TEXT NOT NULL DEFAULT '';
+ALTER TABLE teams ADD COLUMN founded INTEGER NOT NULL DEFAULT 1900;
+ALTER TABLE teams ADD COLUMN primary_color TEXT NOT NULL DEFAULT '#1e293b';
+ALTER TABLE teams ADD COLUMN secondary_color TEXT NOT NULL DEFAULT '#f8fafc';
+
+-- teams are named for their city followed by their mascot, so the city is everything before the
+-- last word. Trimming off every character of the name other than spaces takes that word away
+UPDATE teams SET city = RTRIM(RTRIM(name, REPLACE(name, ' ', '')), ' ');
+UPDATE teams SET city = name WHERE city = '';
+
+UPDATE teams SET abbr = UPPER(SUBSTR(city, 1, 3));
@@ -0,0 +1,14 @@
+-- career mode
+ALTER TABLE ctrl ADD COLUMN managed_team_id INTEGER;
+ALTER TABLE ctrl ADD COLUMN tactic TEXT NOT NULL DEFAULT 'balanced';
+ALTER TABLE ctrl ADD COLUMN training TEXT NOT NULL DEFAULT 'sharpness';
+ALTER TABLE ctrl ADD COLUMN squad_spend INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE ctrl ADD COLUMN job_security INTEGER NOT NULL DEFAULT 50;
+
+CREATE TABLE inbox (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    year INTEGER NOT NULL,
+    wk_no INTEGER NOT NULL,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL
+);
@@ -0,0 +1,7 @@
+-- standings, schedules and team pages all look matchups up by schedule or by team
+CREATE INDEX matchups_by_schedule ON matchups (schedule_id, wk_no);
+CREATE INDEX matchups_by_home_team ON matchups (home_team_id);
+CREATE INDEX matchups_by_away_team ON matchups (away_team_id);
+
+CREATE INDEX teams_by_tier ON teams (tier_id);
+CREATE INDEX tiers_by_league ON tiers (league_id);
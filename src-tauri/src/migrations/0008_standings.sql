@@ -0,0 +1,85 @@
+-- standings table, kept up to date as each week is played rather than worked out on every read
+CREATE TABLE standings (
+    team_id INTEGER NOT NULL,
+    year INTEGER NOT NULL,
+    league_id INTEGER NOT NULL,
+    tier_id INTEGER NOT NULL,
+    wins INTEGER NOT NULL DEFAULT 0,
+    draws INTEGER NOT NULL DEFAULT 0,
+    losses INTEGER NOT NULL DEFAULT 0,
+    points_for INTEGER NOT NULL DEFAULT 0,
+    points_against INTEGER NOT NULL DEFAULT 0,
+    streak INTEGER NOT NULL DEFAULT 0,
+    points INTEGER NOT NULL DEFAULT 0,
+    rank INTEGER NOT NULL DEFAULT 0,
+
+    FOREIGN KEY (team_id) REFERENCES teams (id),
+    CONSTRAINT unique_year_per_team UNIQUE (team_id, year)
+);
+
+CREATE INDEX standings_by_tier ON standings (year, tier_id, rank);
+
+-- fill it in for every season already on the save
+WITH results AS (
+    SELECT
+        schedules.year,
+        matchups.home_team_id AS team_id,
+        schedules.league_id,
+        schedules.tier_id,
+        matchups.wk_no,
+        matchups.home_team_score AS scored,
+        matchups.away_team_score AS conceded
+    FROM matchups
+    INNER JOIN schedules ON schedules.id = matchups.schedule_id
+    UNION ALL
+    SELECT
+        schedules.year,
+        matchups.away_team_id,
+        schedules.league_id,
+        schedules.tier_id,
+        matchups.wk_no,
+        matchups.away_team_score,
+        matchups.home_team_score
+    FROM matchups
+    INNER JOIN schedules ON schedules.id = matchups.schedule_id
+),
+streak_breaks AS (
+    SELECT year, team_id, MAX(wk_no) AS wk_no
+    FROM results
+    WHERE scored <= conceded
+    GROUP BY year, team_id
+)
+INSERT INTO standings (
+    team_id, year, league_id, tier_id, wins, draws, losses, points_for, points_against, streak
+)
+SELECT
+    results.team_id,
+    results.year,
+    results.league_id,
+    results.tier_id,
+    COUNT(CASE WHEN scored > conceded THEN 1 END),
+    COUNT(CASE WHEN scored = conceded THEN 1 END),
+    COUNT(CASE WHEN scored < conceded THEN 1 END),
+    COALESCE(SUM(scored), 0),
+    COALESCE(SUM(conceded), 0),
+    COUNT(CASE WHEN scored > conceded AND results.wk_no > COALESCE(streak_breaks.wk_no, 0) THEN 1 END)
+FROM results
+LEFT JOIN streak_breaks
+    ON streak_breaks.year = results.year AND streak_breaks.team_id = results.team_id
+GROUP BY results.year, results.team_id, results.league_id, results.tier_id;
+
+UPDATE standings SET points = 2 * wins + draws;
+
+UPDATE standings
+SET rank = ranked.rank
+FROM (
+    SELECT
+        team_id,
+        year,
+        ROW_NUMBER() OVER (
+            PARTITION BY year, tier_id
+            ORDER BY points DESC, points_for - points_against DESC, points_for DESC, team_id
+        ) AS rank
+    FROM standings
+) AS ranked
+WHERE standings.team_id = ranked.team_id AND standings.year = ranked.year;
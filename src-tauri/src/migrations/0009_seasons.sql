@@ -0,0 +1,54 @@
+-- seasons table
+CREATE TABLE seasons (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    year INTEGER NOT NULL UNIQUE,
+    -- one of preseason, regular, playoffs or offseason
+    phase TEXT NOT NULL DEFAULT 'preseason',
+    start_wk INTEGER NOT NULL DEFAULT 1,
+    end_wk INTEGER NOT NULL DEFAULT 0
+);
+
+-- the team that finished top of each tier, once a season is over
+CREATE TABLE season_champions (
+    season_id INTEGER NOT NULL,
+    league_id INTEGER NOT NULL,
+    tier_id INTEGER NOT NULL,
+    team_id INTEGER NOT NULL,
+
+    FOREIGN KEY (season_id) REFERENCES seasons (id),
+    FOREIGN KEY (league_id) REFERENCES leagues (id),
+    FOREIGN KEY (tier_id) REFERENCES tiers (id),
+    FOREIGN KEY (team_id) REFERENCES teams (id),
+    CONSTRAINT unique_tier_per_season UNIQUE (season_id, tier_id)
+);
+
+CREATE INDEX matchups_by_season ON matchups (season_id, wk_no);
+
+-- every season already on the save. Past seasons are over, and the current one is under way
+-- once its first week has been played
+INSERT INTO seasons (year, phase, start_wk, end_wk)
+SELECT
+    schedules.year,
+    CASE
+        WHEN schedules.year < ctrl.year THEN 'offseason'
+        WHEN ctrl.wk_no > 1 THEN 'regular'
+        ELSE 'preseason'
+    END,
+    MIN(matchups.wk_no),
+    MAX(matchups.wk_no)
+FROM schedules
+INNER JOIN matchups ON matchups.schedule_id = schedules.id
+CROSS JOIN ctrl
+GROUP BY schedules.year;
+
+UPDATE matchups
+SET season_id = seasons.id
+FROM schedules
+INNER JOIN seasons ON seasons.year = schedules.year
+WHERE schedules.id = matchups.schedule_id;
+
+INSERT INTO season_champions (season_id, league_id, tier_id, team_id)
+SELECT seasons.id, standings.league_id, standings.tier_id, standings.team_id
+FROM standings
+INNER JOIN seasons ON seasons.year = standings.year
+WHERE seasons.phase = 'offseason' AND standings.rank = 1;
@@ -0,0 +1,145 @@
+-- the best ever of each kind of record, for the whole world, each league and each team
+CREATE TABLE records (
+    -- one of biggest_win, highest_scoring_match, longest_win_streak, longest_unbeaten_run,
+    -- longest_losing_streak, most_season_points or most_titles
+    kind TEXT NOT NULL,
+    -- one of world, league or team, along with the league or team it's for (0 for the world)
+    scope TEXT NOT NULL,
+    scope_id INTEGER NOT NULL,
+    value INTEGER NOT NULL,
+    -- the team holding it, unless it belongs to a game rather than a team
+    team_id INTEGER,
+    -- the game it was set in, for records set in a single game
+    matchup_id INTEGER,
+    year INTEGER NOT NULL,
+    wk_no INTEGER NOT NULL,
+
+    FOREIGN KEY (team_id) REFERENCES teams (id),
+    FOREIGN KEY (matchup_id) REFERENCES matchups (id),
+    CONSTRAINT unique_record_per_scope UNIQUE (kind, scope, scope_id)
+);
+
+-- the run of results each team is on, carried over from one season into the next
+CREATE TABLE team_runs (
+    team_id INTEGER PRIMARY KEY,
+    wins INTEGER NOT NULL DEFAULT 0,
+    unbeaten INTEGER NOT NULL DEFAULT 0,
+    losses INTEGER NOT NULL DEFAULT 0,
+
+    FOREIGN KEY (team_id) REFERENCES teams (id)
+);
+
+-- fill both in from every result already on the save, from each side, numbered in the order
+-- each team played them
+CREATE TEMP TABLE backfill_results AS
+WITH results AS (
+    SELECT
+        matchups.id AS matchup_id,
+        schedules.year,
+        matchups.wk_no,
+        schedules.league_id,
+        matchups.home_team_id AS team_id,
+        matchups.home_team_score AS scored,
+        matchups.away_team_score AS conceded,
+        -- the order the game loop offers results up in, home side first
+        matchups.id * 2 AS seq
+    FROM matchups
+    INNER JOIN schedules ON schedules.id = matchups.schedule_id
+    WHERE matchups.home_team_score IS NOT NULL
+    UNION ALL
+    SELECT
+        matchups.id,
+        schedules.year,
+        matchups.wk_no,
+        schedules.league_id,
+        matchups.away_team_id,
+        matchups.away_team_score,
+        matchups.home_team_score,
+        matchups.id * 2 + 1
+    FROM matchups
+    INNER JOIN schedules ON schedules.id = matchups.schedule_id
+    WHERE matchups.home_team_score IS NOT NULL
+)
+SELECT *, ROW_NUMBER() OVER (PARTITION BY team_id ORDER BY year, wk_no) AS n
+FROM results;
+
+-- how long each kind of run was after every game, counting back to the last game that broke it
+CREATE TEMP TABLE backfill_runs AS
+SELECT
+    *,
+    n - MAX(CASE WHEN scored <= conceded THEN n ELSE 0 END) OVER runs AS wins,
+    n - MAX(CASE WHEN scored < conceded THEN n ELSE 0 END) OVER runs AS unbeaten,
+    n - MAX(CASE WHEN scored >= conceded THEN n ELSE 0 END) OVER runs AS losses,
+    SUM(2 * (scored > conceded) + (scored = conceded)) OVER season AS points
+FROM backfill_results
+WINDOW
+    runs AS (PARTITION BY team_id ORDER BY n),
+    season AS (PARTITION BY team_id, year ORDER BY n);
+
+INSERT INTO team_runs (team_id, wins, unbeaten, losses)
+SELECT team_id, wins, unbeaten, losses
+FROM backfill_runs
+WHERE (team_id, n) IN (SELECT team_id, MAX(n) FROM backfill_runs GROUP BY team_id);
+
+-- every value that could have set a record, along with the team whose own record it counts
+-- towards and the team that would hold it
+CREATE TEMP TABLE backfill_candidates AS
+SELECT 'biggest_win' AS kind, team_id AS for_team_id, team_id, league_id, scored - conceded AS value, matchup_id, year, wk_no, seq
+FROM backfill_runs
+WHERE scored > conceded
+UNION ALL
+SELECT 'highest_scoring_match', team_id, NULL, league_id, scored + conceded, matchup_id, year, wk_no, seq
+FROM backfill_runs
+UNION ALL
+SELECT 'longest_win_streak', team_id, team_id, league_id, wins, NULL, year, wk_no, seq
+FROM backfill_runs
+WHERE wins > 0
+UNION ALL
+SELECT 'longest_unbeaten_run', team_id, team_id, league_id, unbeaten, NULL, year, wk_no, seq
+FROM backfill_runs
+WHERE unbeaten > 0
+UNION ALL
+SELECT 'longest_losing_streak', team_id, team_id, league_id, losses, NULL, year, wk_no, seq
+FROM backfill_runs
+WHERE losses > 0
+UNION ALL
+SELECT 'most_season_points', team_id, team_id, league_id, points, NULL, year, wk_no, seq
+FROM backfill_runs
+UNION ALL
+-- titles are counted the week after a season's last fixtures, when it's closed out
+SELECT
+    'most_titles',
+    season_champions.team_id,
+    season_champions.team_id,
+    season_champions.league_id,
+    COUNT(*) OVER (PARTITION BY season_champions.team_id ORDER BY seasons.year),
+    NULL,
+    seasons.year,
+    seasons.end_wk + 1,
+    season_champions.tier_id
+FROM season_champions
+INNER JOIN seasons ON seasons.id = season_champions.season_id;
+
+-- the first to reach the best value of each holds the record, since equalling it isn't enough
+INSERT INTO records (kind, scope, scope_id, value, team_id, matchup_id, year, wk_no)
+SELECT kind, scope, scope_id, value, team_id, matchup_id, year, wk_no
+FROM (
+    SELECT
+        *,
+        ROW_NUMBER() OVER (
+            PARTITION BY kind, scope, scope_id
+            ORDER BY value DESC, year, wk_no, seq
+        ) AS place
+    FROM (
+        SELECT backfill_candidates.*, 'world' AS scope, 0 AS scope_id FROM backfill_candidates
+        UNION ALL
+        SELECT backfill_candidates.*, 'league', league_id FROM backfill_candidates
+        UNION ALL
+        SELECT backfill_candidates.*, 'team', for_team_id FROM backfill_candidates
+    )
+)
+WHERE place = 1;
+
+DROP TABLE backfill_candidates;
+DROP TABLE backfill_runs;
+DROP TABLE backfill_results;
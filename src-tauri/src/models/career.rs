@@ -0,0 +1,327 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::{ctx::OakenCtx, error::OakenResult, sql_args};
+
+use super::{
+    finance::Finances,
+    matchup::{Matchup, Outcome},
+    standings::Standing,
+    team::Team,
+    tier::Tier,
+};
+
+/// Job security a manager starts a new job with, out of 100
+const STARTING_SECURITY: i32 = 50;
+/// How far job security moves for each point of a result above or below the board's target
+const SECURITY_PER_RESULT: f64 = 12.0;
+/// Chance each week that development training adds a point of skill
+const DEVELOPMENT_CHANCE: f64 = 0.25;
+
+/// How the managed team sets up for its matches
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum Tactic {
+    Attacking,
+    Balanced,
+    Defensive,
+}
+
+/// What the managed team works on between matches
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum Training {
+    /// Sharpens the squad up for the next match
+    Sharpness,
+    /// Gives up short term sharpness for a chance to improve the team's skill
+    Development,
+}
+
+/// The weekly decisions a manager makes for their team
+#[derive(Serialize, Deserialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ManagerPlan {
+    pub tactic: Tactic,
+    pub training: Training,
+    /// Percent of the season's budget put towards squad bonuses, from 0 to 100
+    pub squad_spend: i32,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Career {
+    pub managed_team_id: Option<i32>,
+    pub tactic: Tactic,
+    pub training: Training,
+    pub squad_spend: i32,
+    /// How safe the manager's job is, from 0 to 100. The manager is fired when this reaches 0
+    pub job_security: i32,
+}
+
+/// Points a team's plan adds to its own score and to its opponent's
+#[derive(Default)]
+pub struct MatchModifiers {
+    pub scored: i32,
+    pub conceded: i32,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct InboxMessage {
+    pub id: i32,
+    pub year: i32,
+    pub wk_no: i32,
+    pub subject: String,
+    pub body: String,
+}
+
+impl InboxMessage {
+    pub fn send(
+        ctx: &OakenCtx, year: i32, wk_no: i32, subject: &str, body: String,
+    ) -> OakenResult<()> {
+        ctx.pool()?.exec_with(
+            "INSERT INTO inbox (year, wk_no, subject, body) VALUES ($1, $2, $3, $4);",
+            sql_args![year, wk_no, subject, body],
+        )
+    }
+
+    /// Every message the manager has received, newest first
+    pub async fn get_all(ctx: &OakenCtx) -> OakenResult<Vec<Self>> {
+        ctx.pool()?
+            .query("SELECT * FROM inbox ORDER BY id DESC;")
+            .await
+    }
+}
+
+impl Career {
+    pub async fn get(ctx: &OakenCtx) -> OakenResult<Self> {
+        ctx.pool()?
+            .query_one(
+                r#"
+                SELECT managed_team_id, tactic, training, squad_spend, job_security FROM ctrl;
+            "#,
+            )
+            .await
+    }
+
+    /// The share of its games the board expects a team in a tier of this rank to win
+    fn board_target(rank: i32) -> f64 {
+        (0.6 - 0.05 * (rank - 1) as f64).max(0.3)
+    }
+
+    /// Takes over as manager of `team_id`, starting with a clean slate
+    pub async fn take_job(
+        ctx: &OakenCtx, team_id: i32, year: i32, wk_no: i32,
+    ) -> OakenResult<Self> {
+        // look the team up first so that asking for one that doesn't exist changes nothing
+        let team = Team::get(ctx, &team_id).await?;
+        let Tier { rank, name, .. } = Tier::get(ctx, &team.tier_id).await?;
+
+        ctx.pool()?.exec_with(
+            r#"
+            UPDATE ctrl
+            SET
+                managed_team_id = $1,
+                tactic = 'balanced',
+                training = 'sharpness',
+                squad_spend = 0,
+                job_security = $2;
+        "#,
+            sql_args![team_id, STARTING_SECURITY],
+        )?;
+
+        InboxMessage::send(
+            ctx,
+            year,
+            wk_no,
+            "Welcome to the club",
+            format!(
+                "The board is delighted to welcome you as manager of {}. As a {name} side, they \
+                 expect you to win at least {:.0}% of your games.",
+                team.name,
+                Self::board_target(rank) * 100.0
+            ),
+        )?;
+
+        Self::get(ctx).await
+    }
+
+    pub async fn set_plan(ctx: &OakenCtx, plan: ManagerPlan) -> OakenResult<Self> {
+        ctx.pool()?.exec_with(
+            "UPDATE ctrl SET tactic = $1, training = $2, squad_spend = $3;",
+            sql_args![plan.tactic, plan.training, plan.squad_spend.clamp(0, 100)],
+        )?;
+
+        Self::get(ctx).await
+    }
+
+    /// Clears the managed team, leaving every team to be run by the simulation
+    pub fn leave_job(ctx: &OakenCtx) -> OakenResult<()> {
+        ctx.pool()?
+            .exec("UPDATE ctrl SET managed_team_id = NULL;")?;
+
+        Ok(())
+    }
+
+    /// How this week's plan changes the scores in a match played by `team_id`
+    pub fn match_modifiers(&self, team_id: &i32) -> MatchModifiers {
+        if self.managed_team_id != Some(*team_id) {
+            return MatchModifiers::default();
+        }
+
+        let (scored, conceded) = match self.tactic {
+            Tactic::Attacking => (3, 2),
+            Tactic::Balanced => (0, 0),
+            Tactic::Defensive => (-1, -3),
+        };
+
+        let sharpness = match self.training {
+            Training::Sharpness => 1,
+            Training::Development => 0,
+        };
+
+        MatchModifiers {
+            scored: scored + sharpness + self.squad_spend / 20,
+            conceded,
+        }
+    }
+
+    /// Carries out the plan for the week that was just played, and has the board weigh up the
+    /// managed team's result
+    pub async fn review_week(
+        &self, ctx: &OakenCtx, results: &[Matchup], year: i32, wk_no: i32, final_wk: i32,
+    ) -> OakenResult<()> {
+        let Some(team_id) = self.managed_team_id else {
+            return Ok(());
+        };
+
+        let Some(result) = results
+            .iter()
+            .find(|matchup| matchup.home_team_id == team_id || matchup.away_team_id == team_id)
+        else {
+            return Ok(());
+        };
+
+        let team = Team::get(ctx, &team_id).await?;
+
+        if self.training == Training::Development
+            && ctx.rng().gen_bool(DEVELOPMENT_CHANCE)
+            && team.skill < 100
+        {
+            ctx.pool()?.exec_with(
+                "UPDATE teams SET skill = skill + 1 WHERE id = $1;",
+                sql_args![team_id],
+            )?;
+
+            InboxMessage::send(
+                ctx,
+                year,
+                wk_no,
+                "Training report",
+                format!(
+                    "The squad's development work is paying off. {} has improved to a \
+                     skill of {}.",
+                    team.name,
+                    team.skill + 1
+                ),
+            )?;
+        }
+
+        if let Some(finances) = Finances::get(ctx, &team_id, &year).await? {
+            let bonuses = finances.budget / final_wk.max(1) * self.squad_spend / 100;
+            Finances::record_wages(ctx, team_id, &year, bonuses)?;
+        }
+
+        let points = match result.outcome_for(&team_id) {
+            Some(Outcome::Win) => 1.0,
+            Some(Outcome::Draw) => 0.5,
+            _ => 0.0,
+        };
+
+        let Tier { rank, .. } = Tier::get(ctx, &team.tier_id).await?;
+        let change = ((points - Self::board_target(rank)) * SECURITY_PER_RESULT).round() as i32;
+
+        self.adjust_security(ctx, change, year, wk_no).await
+    }
+
+    /// Has the board weigh up where the managed team finished in its table
+    pub async fn review_season(
+        &self, ctx: &OakenCtx, standings: &[Standing], year: i32, wk_no: i32,
+    ) -> OakenResult<()> {
+        let Some(team_id) = self.managed_team_id else {
+            return Ok(());
+        };
+
+        let Some(pos) = standings
+            .iter()
+            .position(|standing| standing.team_id == team_id)
+        else {
+            return Ok(());
+        };
+
+        let third = standings.len() / 3;
+        let (change, verdict) = if pos < third {
+            (20, "The board is thrilled with a top third finish.")
+        } else if pos >= standings.len() - third {
+            (
+                -25,
+                "The board is deeply disappointed with a bottom third finish.",
+            )
+        } else {
+            (0, "The board considers this season a steady one.")
+        };
+
+        InboxMessage::send(
+            ctx,
+            year,
+            wk_no,
+            "Season review",
+            format!(
+                "You finished the season in position {} of {}. {verdict}",
+                pos + 1,
+                standings.len()
+            ),
+        )?;
+
+        self.adjust_security(ctx, change, year, wk_no).await
+    }
+
+    async fn adjust_security(
+        &self, ctx: &OakenCtx, change: i32, year: i32, wk_no: i32,
+    ) -> OakenResult<()> {
+        let security = (self.job_security + change).clamp(0, 100);
+
+        ctx.pool()?
+            .exec_with("UPDATE ctrl SET job_security = $1;", sql_args![security])?;
+
+        if security > 0 {
+            if security < 20 && self.job_security >= 20 {
+                InboxMessage::send(
+                    ctx,
+                    year,
+                    wk_no,
+                    "A warning from the board",
+                    "The board is losing patience. Results need to improve, and quickly."
+                        .to_owned(),
+                )?;
+            }
+
+            return Ok(());
+        }
+
+        InboxMessage::send(
+            ctx,
+            year,
+            wk_no,
+            "You have been fired",
+            "The board has decided to relieve you of your duties, effective immediately. Pick a \
+             new club to manage to carry on your career."
+                .to_owned(),
+        )?;
+
+        Self::leave_job(ctx)
+    }
+}
@@ -0,0 +1,166 @@
+use rand::{seq::SliceRandom, Rng};
+use random_word::Lang;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::{ctx::OakenCtx, error::OakenResult, sql_args, util::Capitalize};
+
+/// How many unemployed coaches are waiting for a job when a new game starts
+pub const POOL_SIZE: usize = 32;
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Coach {
+    pub id: i32,
+    pub name: String,
+    pub rating: i32,
+    pub team_id: Option<i32>,
+}
+
+/// A single coach's time in charge of a team. Spells that are still running have no end
+#[derive(Serialize, Deserialize, Type, Clone, Debug, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct CoachingSpell {
+    pub coach_id: i32,
+    pub coach_name: String,
+    pub rating: i32,
+    pub team_id: i32,
+    pub start_year: i32,
+    pub start_wk: i32,
+    pub end_year: Option<i32>,
+    pub end_wk: Option<i32>,
+}
+
+impl Coach {
+    pub async fn create(ctx: &OakenCtx, name: String, rating: i32) -> OakenResult<Self> {
+        ctx.pool()?
+            .query_one_with(
+                r#"
+                INSERT INTO coaches (name, rating) VALUES ($1, $2);
+                SELECT * FROM coaches WHERE id = last_insert_rowid();
+            "#,
+                sql_args![&name, rating],
+            )
+            .await
+    }
+
+    /// Creates an unemployed coach with a random name and rating
+    pub async fn generate(ctx: &OakenCtx) -> OakenResult<Self> {
+        let (name, rating) = {
+            let mut rng = ctx.rng();
+            let name = [(); 2]
+                .map(|_| {
+                    random_word::all_len(rng.gen_range(4..=8), Lang::En)
+                        .and_then(|words| words.choose(&mut *rng))
+                        .unwrap_or(&"oaken")
+                        .capitalize()
+                })
+                .join(" ");
+
+            (name, rng.gen_range(20..=90))
+        };
+
+        Self::create(ctx, name, rating).await
+    }
+
+    pub async fn get_for_team(ctx: &OakenCtx, team_id: &i32) -> OakenResult<Option<Self>> {
+        let coach = ctx.pool()?
+            .query_with(
+                "SELECT * FROM coaches WHERE team_id = $1;",
+                sql_args![team_id],
+            )
+            .await?
+            .into_iter()
+            .next();
+
+        Ok(coach)
+    }
+
+    pub async fn get_unemployed(ctx: &OakenCtx) -> OakenResult<Vec<Self>> {
+        ctx.pool()?
+            .query("SELECT * FROM coaches WHERE team_id IS NULL;")
+            .await
+    }
+
+    /// Every coach in charge of a team
+    pub async fn get_employed(ctx: &OakenCtx) -> OakenResult<Vec<Self>> {
+        ctx.pool()?
+            .query("SELECT * FROM coaches WHERE team_id IS NOT NULL;")
+            .await
+    }
+
+    /// Picks a random coach out of work, generating a new one if nobody is available
+    pub async fn get_replacement(ctx: &OakenCtx) -> OakenResult<Self> {
+        let pick = Self::get_unemployed(ctx)
+            .await?
+            .choose(&mut *ctx.rng())
+            .cloned();
+
+        match pick {
+            Some(coach) => Ok(coach),
+            None => Self::generate(ctx).await,
+        }
+    }
+
+    /// Every coach to have taken charge of a team, oldest first
+    pub async fn get_history(ctx: &OakenCtx, team_id: &i32) -> OakenResult<Vec<CoachingSpell>> {
+        ctx.pool()?
+            .query_with(
+                r#"
+                SELECT coaching_spells.*, coaches.name AS coach_name, coaches.rating AS rating
+                FROM coaching_spells
+                INNER JOIN coaches ON coaches.id = coaching_spells.coach_id
+                WHERE coaching_spells.team_id = $1
+                ORDER BY start_year, start_wk;
+            "#,
+                sql_args![team_id],
+            )
+            .await
+    }
+
+    pub async fn get_current_spell(&self, ctx: &OakenCtx) -> OakenResult<Option<CoachingSpell>> {
+        let spell = ctx.pool()?
+            .query_with(
+                r#"
+                SELECT coaching_spells.*, coaches.name AS coach_name, coaches.rating AS rating
+                FROM coaching_spells
+                INNER JOIN coaches ON coaches.id = coaching_spells.coach_id
+                WHERE coaching_spells.coach_id = $1 AND coaching_spells.end_year IS NULL;
+            "#,
+                sql_args![self.id],
+            )
+            .await?
+            .into_iter()
+            .next();
+
+        Ok(spell)
+    }
+
+    /// Puts this coach in charge of `team_id` from the given week onwards
+    pub async fn hire(&self, ctx: &OakenCtx, team_id: i32, year: i32, wk: i32) -> OakenResult<()> {
+        ctx.pool()?.exec_with(
+            r#"
+            UPDATE coaches SET team_id = $2 WHERE id = $1;
+            INSERT INTO coaching_spells (coach_id, team_id, start_year, start_wk) VALUES ($1, $2, $3, $4);
+        "#,
+            sql_args![self.id, team_id, year, wk],
+        )
+    }
+
+    /// Ends this coach's current spell after the given week, returning them to the pool
+    pub async fn fire(&self, ctx: &OakenCtx, year: i32, wk: i32) -> OakenResult<()> {
+        ctx.pool()?.exec_with(
+            r#"
+            UPDATE coaches SET team_id = NULL WHERE id = $1;
+            UPDATE coaching_spells SET end_year = $2, end_wk = $3
+            WHERE coach_id = $1 AND end_year IS NULL;
+        "#,
+            sql_args![self.id, year, wk],
+        )
+    }
+
+    /// Points added to (or taken from) the team's strength in each match, from -5 to +5
+    pub fn strength_bonus(&self) -> i32 {
+        (self.rating - 50) / 10
+    }
+}
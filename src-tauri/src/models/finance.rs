@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::{
+    ctx::OakenCtx,
+    error::{OakenError, OakenResult},
+    sql_args,
+};
+
+use super::{
+    matchup::{Matchday, Matchup},
+    standings::Standing,
+    team::Team,
+    tier::Tier,
+};
+
+/// Balance every top tier team starts a fresh game with
+const STARTING_BALANCE: f64 = 10_000_000.0;
+/// Price of a single ticket in the top tier
+const TOP_TIER_TICKET: f64 = 8.0;
+/// Gate takings the board expects from a home game in the top tier
+const TOP_TIER_GATE: f64 = 250_000.0;
+/// Prize money awarded for winning the top tier
+const TOP_TIER_PRIZE: f64 = 5_000_000.0;
+/// Wages paid each week per point of team skill in the top tier
+const WAGE_PER_SKILL: f64 = 2_500.0;
+
+pub const PROMOTION_BONUS: i32 = 2_000_000;
+pub const RELEGATION_PENALTY: i32 = -1_500_000;
+
+/// Scales a top tier amount down to a given tier, with each tier earning 60% of the one above it
+fn tier_scale(rank: i32) -> f64 {
+    0.6_f64.powi(rank - 1)
+}
+
+/// Looks up the scale for the tier with `tier_id` out of a map of tier ids to ranks
+fn tier_scale_of(ranks: &HashMap<i32, i32>, tier_id: &i32) -> OakenResult<f64> {
+    ranks
+        .get(tier_id)
+        .map(|rank| tier_scale(*rank))
+        .ok_or_else(|| OakenError::not_found("tier", tier_id))
+}
+
+async fn get_tier_ranks(ctx: &OakenCtx) -> OakenResult<HashMap<i32, i32>> {
+    let ranks = Tier::get_all(ctx)
+        .await?
+        .into_iter()
+        .map(|tier| (tier.id, tier.rank))
+        .collect();
+
+    Ok(ranks)
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Finances {
+    pub team_id: i32,
+    pub year: i32,
+    pub opening_balance: i32,
+    pub budget: i32,
+    pub gate_revenue: i32,
+    pub prize_money: i32,
+    pub tier_change: i32,
+    pub wages: i32,
+    pub balance: i32,
+}
+
+/// A single line item posted against a team's finances for a season
+enum Entry {
+    Gate(i32),
+    Prize(i32),
+    TierChange(i32),
+    Wages(i32),
+}
+
+impl Entry {
+    fn column(&self) -> &'static str {
+        match self {
+            Entry::Gate(_) => "gate_revenue",
+            Entry::Prize(_) => "prize_money",
+            Entry::TierChange(_) => "tier_change",
+            Entry::Wages(_) => "wages",
+        }
+    }
+
+    /// The amount this entry moves the balance by. Wages are recorded as a positive bill
+    fn delta(&self) -> i32 {
+        match self {
+            Entry::Gate(amt) | Entry::Prize(amt) | Entry::TierChange(amt) => *amt,
+            Entry::Wages(amt) => -amt,
+        }
+    }
+
+    fn amount(&self) -> i32 {
+        match self {
+            Entry::Gate(amt) | Entry::Prize(amt) | Entry::TierChange(amt) | Entry::Wages(amt) => {
+                *amt
+            }
+        }
+    }
+}
+
+impl Finances {
+    pub async fn get(ctx: &OakenCtx, team_id: &i32, year: &i32) -> OakenResult<Option<Self>> {
+        let finances = ctx.pool()?
+            .query_with(
+                "SELECT * FROM finances WHERE team_id = $1 AND year = $2;",
+                sql_args![team_id, year],
+            )
+            .await?
+            .into_iter()
+            .next();
+
+        Ok(finances)
+    }
+
+    /// Opens the books for `year`, carrying over each team's balance from the previous season
+    pub async fn open_season(ctx: &OakenCtx, year: i32) -> OakenResult<()> {
+        let ranks = get_tier_ranks(ctx).await?;
+
+        for team in Team::get_all(ctx).await? {
+            let scale = tier_scale_of(&ranks, &team.tier_id)?;
+            let opening_balance = match Self::get(ctx, &team.id, &(year - 1)).await? {
+                Some(prev) => prev.balance,
+                None => (STARTING_BALANCE * scale) as i32,
+            };
+
+            // the board lets a team spend its tier's expected income, plus a share of any savings
+            let budget = (TOP_TIER_GATE * scale) as i32 * 10 + opening_balance.max(0) / 4;
+
+            ctx.pool()?.exec_with(
+                r#"
+                INSERT INTO finances (team_id, year, opening_balance, budget, balance)
+                VALUES ($1, $2, $3, $4, $3);
+            "#,
+                sql_args![team.id, year, opening_balance, budget],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Posts ticket sales to each home team and the weekly wage bill to every team playing, out of
+    /// the teams read in for `matchday`
+    pub fn settle_week(
+        ctx: &OakenCtx, year: &i32, results: &[Matchup], matchday: &Matchday,
+    ) -> OakenResult<()> {
+        for matchup in results {
+            let home = matchday.team(&matchup.home_team_id)?;
+            let scale = tier_scale_of(matchday.tier_ranks(), &home.tier_id)?;
+            let gate = matchup.attendance.unwrap_or(0) as f64 * TOP_TIER_TICKET * scale;
+
+            Self::post(ctx, home.id, year, Entry::Gate(gate as i32))?;
+
+            // both sides share a division, so the home tier also sets the away team's wage scale
+            for team_id in [matchup.home_team_id, matchup.away_team_id] {
+                let skill = matchday.team(&team_id)?.skill;
+                let wages = skill as f64 * WAGE_PER_SKILL * scale;
+                Self::post(ctx, team_id, year, Entry::Wages(wages as i32))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Awards prize money by final position within each tier
+    pub async fn settle_season(
+        ctx: &OakenCtx, year: &i32, tables: &HashMap<i32, Vec<Standing>>,
+    ) -> OakenResult<()> {
+        for (tier_id, table) in tables {
+            let Tier { rank, .. } = Tier::get(ctx, tier_id).await?;
+            let pot = TOP_TIER_PRIZE * tier_scale(rank);
+            let size = table.len() as f64;
+
+            for (pos, standing) in table.iter().enumerate() {
+                let prize = pot * (size - pos as f64) / size;
+                Self::post(ctx, standing.team_id, year, Entry::Prize(prize as i32))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records the bonus or penalty for a team moving between tiers at the end of `year`
+    pub fn record_tier_change(
+        ctx: &OakenCtx, team_id: i32, year: &i32, amt: i32,
+    ) -> OakenResult<()> {
+        Self::post(ctx, team_id, year, Entry::TierChange(amt))
+    }
+
+    /// Records wages paid on top of the weekly bill, such as squad bonuses
+    pub fn record_wages(ctx: &OakenCtx, team_id: i32, year: &i32, amt: i32) -> OakenResult<()> {
+        Self::post(ctx, team_id, year, Entry::Wages(amt))
+    }
+
+    fn post(ctx: &OakenCtx, team_id: i32, year: &i32, entry: Entry) -> OakenResult<()> {
+        ctx.pool()?.exec_with(
+            &format!(
+                r#"
+                UPDATE finances
+                SET {col} = {col} + $3, balance = balance + $4
+                WHERE team_id = $1 AND year = $2;
+            "#,
+                col = entry.column()
+            ),
+            sql_args![team_id, year, entry.amount(), entry.delta()],
+        )
+    }
+}
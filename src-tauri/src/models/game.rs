@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
-use crate::{shared::pool::get_pool, sql_args};
+use crate::{ctx::OakenCtx, error::OakenResult, sql_args};
 
 #[derive(Serialize, Deserialize, sqlx::FromRow, Type)]
 pub struct GameState {
@@ -11,17 +11,12 @@ pub struct GameState {
 }
 
 impl GameState {
-    pub async fn get() -> Self {
-        get_pool()
-            .query("SELECT * FROM ctrl;")
-            .await
-            .into_iter()
-            .nth(0)
-            .unwrap()
+    pub async fn get(ctx: &OakenCtx) -> OakenResult<Self> {
+        ctx.pool()?.query_one("SELECT * FROM ctrl;").await
     }
 
-    pub async fn set_week(wk: i32) {
-        get_pool().exec_with(
+    pub async fn set_week(ctx: &OakenCtx, wk: i32) -> OakenResult<()> {
+        ctx.pool()?.exec_with(
             r#"
             UPDATE ctrl
             SET wk_no = $1;
@@ -29,4 +24,14 @@ impl GameState {
             sql_args![wk],
         )
     }
+
+    pub async fn set_year(ctx: &OakenCtx, year: i32) -> OakenResult<()> {
+        ctx.pool()?.exec_with(
+            r#"
+            UPDATE ctrl
+            SET year = $1;
+        "#,
+            sql_args![year],
+        )
+    }
 }
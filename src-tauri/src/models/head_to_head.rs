@@ -0,0 +1,118 @@
+use itertools::Itertools;
+use serde::Serialize;
+use specta::Type;
+
+use crate::{
+    ctx::OakenCtx,
+    error::{OakenError, OakenResult},
+};
+
+use super::{
+    matchup::{Matchup, Outcome},
+    team::Team,
+};
+
+/// The run of results two teams are on against each other, from the first team's side
+#[derive(Serialize, Type, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadToHeadStreak {
+    pub outcome: Outcome,
+    /// How many meetings in a row, up to the latest, have ended this way
+    pub len: i32,
+}
+
+/// Every meeting between two teams across all years, with totals from `team_a_id`'s side
+#[derive(Serialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadToHead {
+    pub team_a_id: i32,
+    pub team_b_id: i32,
+    /// Oldest first, including those yet to be played
+    pub meetings: Vec<Matchup>,
+    pub team_a_wins: i32,
+    pub draws: i32,
+    pub team_b_wins: i32,
+    pub team_a_points: i32,
+    pub team_b_points: i32,
+    /// Team A's win over team B by the widest margin, the earliest if several are as wide
+    pub team_a_biggest_win: Option<Matchup>,
+    pub team_b_biggest_win: Option<Matchup>,
+    /// Teams that have never finished a game against each other have none
+    pub streak: Option<HeadToHeadStreak>,
+}
+
+/// `team_id`'s widest win among `played`
+fn biggest_win<'m>(played: &[&'m Matchup], team_id: &i32) -> Option<&'m Matchup> {
+    played
+        .iter()
+        .filter_map(|matchup| {
+            let (ours, theirs) = matchup.scores_for(team_id)?;
+            (ours > theirs).then_some((ours - theirs, *matchup))
+        })
+        // the earliest of equally wide wins, since max_by_key keeps the last
+        .rev()
+        .max_by_key(|(margin, _)| *margin)
+        .map(|(_, matchup)| matchup)
+}
+
+impl HeadToHead {
+    pub async fn get(ctx: &OakenCtx, team_a_id: i32, team_b_id: i32) -> OakenResult<Self> {
+        if team_a_id == team_b_id {
+            return Err(OakenError::InvalidArgument(format!(
+                "team {team_a_id} can't play itself"
+            )));
+        }
+
+        // make sure both exist, so a typo isn't mistaken for two teams that have never met
+        Team::get(ctx, &team_a_id).await?;
+        Team::get(ctx, &team_b_id).await?;
+
+        let meetings = Matchup::get_with_teamid(ctx, &team_a_id)
+            .await?
+            .into_iter()
+            .filter(|matchup| {
+                matchup.home_team_id == team_b_id || matchup.away_team_id == team_b_id
+            })
+            .sorted_by_key(|matchup| (matchup.season_id, matchup.wk_no))
+            .collect_vec();
+
+        let played = meetings
+            .iter()
+            .filter(|matchup| matchup.outcome_for(&team_a_id).is_some())
+            .collect_vec();
+
+        let outcomes = played
+            .iter()
+            .filter_map(|matchup| matchup.outcome_for(&team_a_id))
+            .collect_vec();
+        let count = |outcome| outcomes.iter().filter(|each| **each == outcome).count() as i32;
+
+        let (team_a_points, team_b_points) = played
+            .iter()
+            .filter_map(|matchup| matchup.scores_for(&team_a_id))
+            .fold((0, 0), |(a, b), (ours, theirs)| (a + ours, b + theirs));
+
+        let streak = outcomes.last().map(|latest| HeadToHeadStreak {
+            outcome: *latest,
+            len: outcomes
+                .iter()
+                .rev()
+                .take_while(|outcome| *outcome == latest)
+                .count() as i32,
+        });
+
+        Ok(Self {
+            team_a_id,
+            team_b_id,
+            team_a_wins: count(Outcome::Win),
+            draws: count(Outcome::Draw),
+            team_b_wins: count(Outcome::Loss),
+            team_a_points,
+            team_b_points,
+            team_a_biggest_win: biggest_win(&played, &team_a_id).cloned(),
+            team_b_biggest_win: biggest_win(&played, &team_b_id).cloned(),
+            streak,
+            meetings,
+        })
+    }
+}
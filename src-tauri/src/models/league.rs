@@ -1,8 +1,12 @@
-use futures::future::join_all;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use tap::Pipe;
 
-use crate::{shared::pool::get_pool, sql_args};
+use crate::{
+    ctx::OakenCtx,
+    error::{OakenError, OakenResult},
+    sql_args,
+};
 
 use super::tier::Tier;
 
@@ -14,9 +18,9 @@ pub struct League {
 }
 
 impl League {
-    pub async fn create(name: String, abbr: String) -> Self {
-        get_pool()
-            .query_with(
+    pub async fn create(ctx: &OakenCtx, name: String, abbr: String) -> OakenResult<Self> {
+        ctx.pool()?
+            .query_one_with(
                 r#"
             INSERT INTO leagues (name, abbr) VALUES ($1, $2);
             SELECT * FROM leagues WHERE id = last_insert_rowid();
@@ -24,40 +28,37 @@ impl League {
                 sql_args![&name, &abbr],
             )
             .await
-            .into_iter()
-            .nth(0)
-            .unwrap()
     }
 
-    pub async fn get(id: &i32) -> League {
-        get_pool()
+    pub async fn get(ctx: &OakenCtx, id: &i32) -> OakenResult<League> {
+        ctx.pool()?
             .query_with("SELECT * FROM leagues WHERE id = $1", sql_args![id])
-            .await
+            .await?
             .into_iter()
             .nth(0)
-            .expect(&format!("failed to look up league by id: {id}"))
+            .ok_or_else(|| OakenError::not_found("league", id))
     }
 
-    pub async fn get_by_name(name: String) -> League {
-        get_pool()
+    pub async fn get_by_name(ctx: &OakenCtx, name: String) -> OakenResult<League> {
+        ctx.pool()?
             .query_with("SELECT * FROM leagues WHERE name = $1", sql_args![&name])
-            .await
+            .await?
             .into_iter()
             .nth(0)
-            .expect(&format!("failed to find league by name: {name}"))
+            .ok_or_else(|| OakenError::not_found("league", name))
     }
 
-    pub async fn get_all() -> Vec<League> {
-        get_pool().query("SELECT * FROM leagues;").await
+    pub async fn get_all(ctx: &OakenCtx) -> OakenResult<Vec<League>> {
+        ctx.pool()?.query("SELECT * FROM leagues;").await
     }
 
-    pub async fn get_tiers(&self) -> Vec<Tier> {
-        get_pool()
+    pub async fn get_tiers(&self, ctx: &OakenCtx) -> OakenResult<Vec<Tier>> {
+        ctx.pool()?
             .query_with(
                 "SELECT * FROM tiers WHERE league_id = $1;",
                 sql_args![self.id],
             )
-            .await as Vec<Tier>
+            .await
     }
 }
 
@@ -70,25 +71,36 @@ pub struct LeagueInfo {
 }
 
 impl LeagueInfo {
-    pub async fn get(league_id: i32) -> LeagueInfo {
-        let league = League::get(&league_id).await;
-        let tiers = league.get_tiers().await;
+    pub async fn get(ctx: &OakenCtx, league_id: i32) -> OakenResult<LeagueInfo> {
+        let league = League::get(ctx, &league_id).await?;
+        let tiers = league.get_tiers(ctx).await?;
         let League { id, name, abbr } = league;
 
-        Self {
+        Ok(Self {
             id,
             name,
             abbr,
             tiers,
-        }
+        })
     }
 
-    pub async fn get_all() -> Vec<Self> {
-        League::get_all()
-            .await
+    pub async fn get_all(ctx: &OakenCtx) -> OakenResult<Vec<Self>> {
+        let mut tiers = ctx.pool()?
+            .query::<Tier>("SELECT * FROM tiers ORDER BY id;")
+            .await?
             .into_iter()
-            .map(|league| async move { Self::get(league.id).await })
-            .pipe(|it| join_all(it))
-            .await
+            .into_group_map_by(|tier| tier.league_id);
+
+        League::get_all(ctx)
+            .await?
+            .into_iter()
+            .map(|League { id, name, abbr }| Self {
+                tiers: tiers.remove(&id).unwrap_or_default(),
+                id,
+                name,
+                abbr,
+            })
+            .collect_vec()
+            .pipe(Ok)
     }
 }
@@ -1,29 +1,187 @@
+use std::{collections::HashMap, ops::Range};
+
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
-use crate::{inline_async, shared::pool::get_pool, sql_args};
+use crate::{
+    ctx::OakenCtx,
+    error::{OakenError, OakenResult},
+    sql_args,
+};
+
+use super::{career::Career, coach::Coach, team::Team, tier::Tier};
 
-use super::team::Team;
+/// Most points a sold out stadium is worth to the home side
+const HOME_ADVANTAGE: f64 = 2.0;
+
+/// How far either side's score can swing from what its strength alone would give it
+const SCORE_NOISE: Range<i32> = -2..5;
 
 #[derive(Serialize, Deserialize, Type, Clone, Debug, sqlx::FromRow)]
 #[serde(rename_all = "camelCase")]
 pub struct Matchup {
     pub id: i32,
     pub wk_no: i32,
+    pub season_id: i32,
     pub home_team_id: i32,
     pub away_team_id: i32,
 
     pub home_team_score: Option<i32>,
     pub away_team_score: Option<i32>,
+    pub attendance: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Outcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// Most matchups [`Matchup::search`] returns in one page
+const MAX_PAGE_LEN: u32 = 500;
+
+/// How many matchups [`Matchup::search`] returns in one page when no limit is given
+const DEFAULT_PAGE_LEN: u32 = 50;
+
+/// A matchup along with when, where and between whom it was played, as returned by
+/// [`Matchup::search`]
+#[derive(Serialize, Type, Clone, Debug, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchupDetail {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub matchup: Matchup,
+    pub year: i32,
+    pub league_id: i32,
+    pub tier_id: i32,
+    pub home_team_name: String,
+    pub away_team_name: String,
+}
+
+/// A [`MatchupDetail`] along with the key it was sorted by, to pick up from in the next page
+#[derive(sqlx::FromRow)]
+struct MatchupSearchRow {
+    #[sqlx(flatten)]
+    detail: MatchupDetail,
+    sort_key: i32,
+}
+
+#[derive(Deserialize, Type, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchupSort {
+    #[default]
+    Date,
+    Attendance,
+    /// How far apart the two sides finished
+    Margin,
+    /// How many points both sides scored between them
+    TotalScore,
+}
+
+impl MatchupSort {
+    /// The integer matchups are ordered by. Unplayed matchups come before every played one
+    fn key(self) -> &'static str {
+        match self {
+            Self::Date => "seasons.year * 1000 + matchups.wk_no",
+            Self::Attendance => "COALESCE(matchups.attendance, -1)",
+            Self::Margin => {
+                "COALESCE(ABS(matchups.home_team_score - matchups.away_team_score), -1)"
+            }
+            Self::TotalScore => "COALESCE(matchups.home_team_score + matchups.away_team_score, -1)",
+        }
+    }
+}
+
+/// Which matchups [`Matchup::search`] should return, and in what order. Every filter left out
+/// matches everything
+#[derive(Deserialize, Type, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchupSearch {
+    #[serde(default)]
+    pub year: Option<i32>,
+    /// The first week to include
+    #[serde(default)]
+    pub from_wk: Option<i32>,
+    /// The last week to include
+    #[serde(default)]
+    pub to_wk: Option<i32>,
+    #[serde(default)]
+    pub league_id: Option<i32>,
+    #[serde(default)]
+    pub tier_id: Option<i32>,
+    /// Only matchups this team plays in
+    #[serde(default)]
+    pub team_id: Option<i32>,
+    /// Only matchups against this team
+    #[serde(default)]
+    pub opponent_id: Option<i32>,
+    #[serde(default)]
+    pub played: Option<bool>,
+    /// Only matchups that ended this way for `team_id`, or for the home side when no team is
+    /// given. Unplayed matchups never match
+    #[serde(default)]
+    pub result: Option<Outcome>,
+    #[serde(default)]
+    pub sort: MatchupSort,
+    #[serde(default)]
+    pub descending: bool,
+    /// Where the previous page left off, as given by its `nextCursor`
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchupPage {
+    pub matchups: Vec<MatchupDetail>,
+    /// Pass back as `cursor` to get the next page, or `None` if this was the last one
+    pub next_cursor: Option<String>,
+}
+
+/// Encodes where a page of [`Matchup::search`] ended, as `<sort key>:<matchup id>`
+fn encode_cursor(sort_key: i32, id: i32) -> String {
+    format!("{sort_key}:{id}")
+}
+
+fn decode_cursor(cursor: &str) -> OakenResult<(i32, i32)> {
+    cursor
+        .split_once(':')
+        .and_then(|(key, id)| Some((key.parse().ok()?, id.parse().ok()?)))
+        .ok_or_else(|| OakenError::InvalidArgument(format!("bad cursor '{cursor}'")))
 }
 
 impl Matchup {
+    /// The score of `team_id` in this matchup followed by its opponent's, if it has been played
+    pub fn scores_for(&self, team_id: &i32) -> Option<(i32, i32)> {
+        if self.home_team_id == *team_id {
+            Some((self.home_team_score?, self.away_team_score?))
+        } else {
+            Some((self.away_team_score?, self.home_team_score?))
+        }
+    }
+
+    /// The result of this matchup from the point of view of `team_id`, if it has been played
+    pub fn outcome_for(&self, team_id: &i32) -> Option<Outcome> {
+        let (ours, theirs) = self.scores_for(team_id)?;
+
+        Some(match ours.cmp(&theirs) {
+            std::cmp::Ordering::Greater => Outcome::Win,
+            std::cmp::Ordering::Equal => Outcome::Draw,
+            std::cmp::Ordering::Less => Outcome::Loss,
+        })
+    }
+
     pub async fn create(
-        home_team_id: i32, away_team_id: i32, wk_no: i32, season_id: i32, schedule_id: i32,
-    ) -> Self {
-        get_pool()
-            .query_with(
+        ctx: &OakenCtx, home_team_id: i32, away_team_id: i32, wk_no: i32, season_id: i32,
+        schedule_id: i32,
+    ) -> OakenResult<Self> {
+        ctx.pool()?
+            .query_one_with(
                 r#"
                 INSERT INTO matchups (wk_no, season_id, home_team_id, away_team_id, schedule_id)
                 VALUES ($1, $2, $3, $4, $5);
@@ -32,13 +190,19 @@ impl Matchup {
                 sql_args![wk_no, season_id, home_team_id, away_team_id, schedule_id],
             )
             .await
+    }
+
+    pub async fn get(ctx: &OakenCtx, id: &i32) -> OakenResult<Self> {
+        ctx.pool()?
+            .query_with("SELECT * FROM matchups WHERE id = $1;", sql_args![id])
+            .await?
             .into_iter()
             .nth(0)
-            .unwrap()
+            .ok_or_else(|| OakenError::not_found("matchup", id))
     }
 
-    pub async fn get_with_teamid(team_id: &i32) -> Vec<Self> {
-        get_pool()
+    pub async fn get_with_teamid(ctx: &OakenCtx, team_id: &i32) -> OakenResult<Vec<Self>> {
+        ctx.pool()?
             .query_with(
                 r#"
                 SELECT * FROM matchups
@@ -49,30 +213,442 @@ impl Matchup {
             .await
     }
 
-    pub async fn get_all_for_schedule(schedule_id: i32) -> Vec<Self> {
-        get_pool().query_with("SELECT * FROM matchups WHERE schedule_id = $1;", sql_args![schedule_id]).await
+    /// Every fixture in week `wk_no` of the season, across all leagues and tiers
+    pub async fn get_for_week(
+        ctx: &OakenCtx, season_id: &i32, wk_no: &i32,
+    ) -> OakenResult<Vec<Self>> {
+        ctx.pool()?
+            .query_with(
+                "SELECT * FROM matchups WHERE season_id = $1 AND wk_no = $2;",
+                sql_args![season_id, wk_no],
+            )
+            .await
     }
 
-    /// Computes the scores for this matchup refetching and returning the result once done
-    pub fn compute_scores(&self) {
-        let mut rng = rand::thread_rng();
-        let pool = get_pool();
+    /// Every fixture in the season, played or not
+    pub async fn get_for_season(ctx: &OakenCtx, season_id: &i32) -> OakenResult<Vec<Self>> {
+        ctx.pool()?
+            .query_with(
+                "SELECT * FROM matchups WHERE season_id = $1;",
+                sql_args![season_id],
+            )
+            .await
+    }
 
-        inline_async! {{
-            let home_team = Team::get(&self.home_team_id).await;
-            let away_team = Team::get(&self.away_team_id).await;
+    /// Every matchup matching `search`, one page at a time
+    pub async fn search(ctx: &OakenCtx, search: &MatchupSearch) -> OakenResult<MatchupPage> {
+        let limit = search
+            .limit
+            .unwrap_or(DEFAULT_PAGE_LEN)
+            .clamp(1, MAX_PAGE_LEN);
+        let (after_key, after_id) = search
+            .cursor
+            .as_deref()
+            .map(decode_cursor)
+            .transpose()?
+            .unzip();
 
-            let home_team_score = (home_team.skill + rng.gen_range(-2..5)).max(0);
-            let away_team_score = (away_team.skill + rng.gen_range(-2..5)).max(0);
+        let (cmp, dir) = if search.descending {
+            ("<", "DESC")
+        } else {
+            (">", "ASC")
+        };
 
-            pool.exec_with(
-                r#"
-                    UPDATE matchups
-                    SET home_team_score = $2, away_team_score = $3
-                    WHERE id = $1;
-                "#,
-                sql_args![self.id, home_team_score, away_team_score],
+        let result = search.result.map(|outcome| match outcome {
+            Outcome::Win => "win",
+            Outcome::Draw => "draw",
+            Outcome::Loss => "loss",
+        });
+
+        let sql = format!(
+            r#"
+            SELECT * FROM (
+                SELECT
+                    matchups.*,
+                    seasons.year,
+                    schedules.league_id,
+                    schedules.tier_id,
+                    home_teams.name AS home_team_name,
+                    away_teams.name AS away_team_name,
+                    {key} AS sort_key,
+                    CASE WHEN matchups.away_team_id = $6
+                        THEN matchups.away_team_score - matchups.home_team_score
+                        ELSE matchups.home_team_score - matchups.away_team_score
+                    END AS diff
+                FROM matchups
+                INNER JOIN seasons ON seasons.id = matchups.season_id
+                INNER JOIN schedules ON schedules.id = matchups.schedule_id
+                INNER JOIN teams AS home_teams ON home_teams.id = matchups.home_team_id
+                INNER JOIN teams AS away_teams ON away_teams.id = matchups.away_team_id
+            )
+            WHERE ($1 IS NULL OR year = $1)
+                AND ($2 IS NULL OR wk_no >= $2)
+                AND ($3 IS NULL OR wk_no <= $3)
+                AND ($4 IS NULL OR league_id = $4)
+                AND ($5 IS NULL OR tier_id = $5)
+                AND ($6 IS NULL OR home_team_id = $6 OR away_team_id = $6)
+                AND ($7 IS NULL OR home_team_id = $7 OR away_team_id = $7)
+                AND ($8 IS NULL OR (home_team_score IS NOT NULL) = $8)
+                AND ($9 IS NULL
+                    OR ($9 = 'win' AND diff > 0)
+                    OR ($9 = 'draw' AND diff = 0)
+                    OR ($9 = 'loss' AND diff < 0))
+                AND ($10 IS NULL OR sort_key {cmp} $10 OR (sort_key = $10 AND id {cmp} $11))
+            ORDER BY sort_key {dir}, id {dir}
+            LIMIT $12;
+        "#,
+            key = search.sort.key(),
+        );
+
+        let mut matchups = ctx
+            .pool()?
+            .query_with::<MatchupSearchRow>(
+                &sql,
+                sql_args![
+                    search.year,
+                    search.from_wk,
+                    search.to_wk,
+                    search.league_id,
+                    search.tier_id,
+                    search.team_id,
+                    search.opponent_id,
+                    search.played,
+                    result,
+                    after_key,
+                    after_id,
+                    limit + 1,
+                ],
+            )
+            .await?;
+
+        // one more than was asked for was fetched to tell whether another page follows
+        let next_cursor = (matchups.len() > limit as usize)
+            .then(|| {
+                matchups.truncate(limit as usize);
+                matchups.last()
+            })
+            .flatten()
+            .map(|row| encode_cursor(row.sort_key, row.detail.matchup.id));
+
+        Ok(MatchupPage {
+            matchups: matchups.into_iter().map(|row| row.detail).collect(),
+            next_cursor,
+        })
+    }
+
+    pub async fn get_all_for_schedule(ctx: &OakenCtx, schedule_id: i32) -> OakenResult<Vec<Self>> {
+        ctx.pool()?
+            .query_with(
+                "SELECT * FROM matchups WHERE schedule_id = $1;",
+                sql_args![schedule_id],
             )
-        }}
+            .await
+    }
+
+    /// Works out the crowd for this matchup from the home team's stadium, its tier and form, and
+    /// the stature of the visiting team
+    fn compute_attendance(
+        ctx: &OakenCtx, matchday: &Matchday, home_team: &Team, away_team: &Team,
+    ) -> OakenResult<i32> {
+        let rank = matchday
+            .tier_ranks
+            .get(&home_team.tier_id)
+            .ok_or_else(|| OakenError::not_found("tier", home_team.tier_id))?;
+        let form = matchday.forms.get(&home_team.id).copied().unwrap_or(0.5);
+        let stature = away_team.skill as f64 / 100.0;
+
+        // a top tier stadium is 85% full on an average day, and each tier down draws 10% fewer
+        let base = (0.85 - 0.1 * (rank - 1) as f64).max(0.3);
+        let noise = ctx.rng().gen_range(-0.05..0.05);
+        let fill = (base + 0.25 * (form - 0.5) + 0.15 * (stature - 0.5) + noise).clamp(0.05, 1.0);
+
+        Ok((home_team.capacity as f64 * fill) as i32)
+    }
+
+    /// Computes the attendance and scores for this matchup, returning the result once saved
+    pub fn compute_scores(&self, ctx: &OakenCtx, matchday: &Matchday) -> OakenResult<Self> {
+        let home_team = matchday.team(&self.home_team_id)?;
+        let away_team = matchday.team(&self.away_team_id)?;
+
+        let attendance = Self::compute_attendance(ctx, matchday, home_team, away_team)?;
+        let home_advantage =
+            (HOME_ADVANTAGE * attendance as f64 / home_team.capacity as f64).round() as i32;
+
+        let home_coaching = matchday.coaching(&home_team.id);
+        let away_coaching = matchday.coaching(&away_team.id);
+
+        let home_plan = matchday.career.match_modifiers(&home_team.id);
+        let away_plan = matchday.career.match_modifiers(&away_team.id);
+
+        let (home_noise, away_noise) = {
+            let mut rng = ctx.rng();
+            (rng.gen_range(SCORE_NOISE), rng.gen_range(SCORE_NOISE))
+        };
+
+        let home_team_score = (home_team.skill
+            + home_coaching
+            + home_advantage
+            + home_plan.scored
+            + away_plan.conceded
+            + home_noise)
+            .max(0);
+        let away_team_score =
+            (away_team.skill + away_coaching + away_plan.scored + home_plan.conceded + away_noise)
+                .max(0);
+
+        ctx.pool()?.exec_with(
+            r#"
+            UPDATE matchups
+            SET home_team_score = $2, away_team_score = $3, attendance = $4
+            WHERE id = $1;
+        "#,
+            sql_args![self.id, home_team_score, away_team_score, attendance],
+        )?;
+
+        Ok(Self {
+            home_team_score: Some(home_team_score),
+            away_team_score: Some(away_team_score),
+            attendance: Some(attendance),
+            ..self.clone()
+        })
+    }
+}
+
+/// Everything about the teams playing in a week that goes into their results, read in once for
+/// the whole week rather than for every matchup
+pub struct Matchday {
+    teams: HashMap<i32, Team>,
+    tier_ranks: HashMap<i32, i32>,
+    /// The strength each team's coach adds, for teams with a coach
+    coaching: HashMap<i32, i32>,
+    forms: HashMap<i32, f64>,
+    career: Career,
+}
+
+impl Matchday {
+    pub async fn load(ctx: &OakenCtx, year: &i32) -> OakenResult<Self> {
+        Ok(Self {
+            teams: Team::get_all(ctx)
+                .await?
+                .into_iter()
+                .map(|team| (team.id, team))
+                .collect(),
+            tier_ranks: Tier::get_all(ctx)
+                .await?
+                .into_iter()
+                .map(|tier| (tier.id, tier.rank))
+                .collect(),
+            coaching: Coach::get_employed(ctx)
+                .await?
+                .into_iter()
+                .filter_map(|coach| Some((coach.team_id?, coach.strength_bonus())))
+                .collect(),
+            forms: Team::get_forms(ctx, year).await?,
+            career: Career::get(ctx).await?,
+        })
+    }
+
+    pub fn team(&self, id: &i32) -> OakenResult<&Team> {
+        self.teams
+            .get(id)
+            .ok_or_else(|| OakenError::not_found("team", id))
+    }
+
+    /// Every tier's rank, by tier id
+    pub fn tier_ranks(&self) -> &HashMap<i32, i32> {
+        &self.tier_ranks
+    }
+
+    fn coaching(&self, team_id: &i32) -> i32 {
+        self.coaching.get(team_id).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::{
+        models::{game::GameState, team::TeamInfo},
+        paths::Paths,
+        shared::testing,
+    };
+
+    use super::*;
+
+    /// What [`MatchupSort::key`] works out in SQL, worked out again from a result
+    fn sort_key(detail: &MatchupDetail, sort: MatchupSort) -> i32 {
+        let matchup = &detail.matchup;
+        let scores = matchup.home_team_score.zip(matchup.away_team_score);
+
+        match sort {
+            MatchupSort::Date => detail.year * 1000 + matchup.wk_no,
+            MatchupSort::Attendance => matchup.attendance.unwrap_or(-1),
+            MatchupSort::Margin => scores.map_or(-1, |(home, away)| (home - away).abs()),
+            MatchupSort::TotalScore => scores.map_or(-1, |(home, away)| home + away),
+        }
+    }
+
+    /// Every page of `search`, following each `next_cursor` until there are none left
+    async fn all_pages(ctx: &OakenCtx, mut search: MatchupSearch) -> Vec<MatchupPage> {
+        let mut pages = vec![];
+
+        loop {
+            let page = Matchup::search(ctx, &search).await.unwrap();
+            search.cursor = page.next_cursor.clone();
+            pages.push(page);
+
+            if search.cursor.is_none() {
+                return pages;
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn paging_visits_every_matchup_once_in_order() {
+        let ctx = testing::new_game(3).await;
+
+        // part way into the season, so unplayed games all share the same key
+        testing::play_weeks(&ctx, 3).await;
+
+        let year = GameState::get(&ctx).await.unwrap().year;
+        let tier_id = TeamInfo::get_all(&ctx).await.unwrap()[0].tier.id;
+
+        for sort in [
+            MatchupSort::Date,
+            MatchupSort::Attendance,
+            MatchupSort::Margin,
+            MatchupSort::TotalScore,
+        ] {
+            for descending in [false, true] {
+                let search = MatchupSearch {
+                    year: Some(year),
+                    tier_id: Some(tier_id),
+                    sort,
+                    descending,
+                    ..Default::default()
+                };
+
+                let everything = Matchup::search(
+                    &ctx,
+                    &MatchupSearch {
+                        limit: Some(MAX_PAGE_LEN),
+                        ..search.clone()
+                    },
+                )
+                .await
+                .unwrap();
+
+                assert!(everything.next_cursor.is_none());
+
+                let pages = all_pages(
+                    &ctx,
+                    MatchupSearch {
+                        limit: Some(7),
+                        ..search.clone()
+                    },
+                )
+                .await;
+
+                // every page but the last is full
+                for page in &pages[..pages.len() - 1] {
+                    assert_eq!(page.matchups.len(), 7);
+                }
+
+                let paged = pages
+                    .iter()
+                    .flat_map(|page| &page.matchups)
+                    .map(|detail| (sort_key(detail, sort), detail.matchup.id))
+                    .collect_vec();
+
+                let expected = everything
+                    .matchups
+                    .iter()
+                    .map(|detail| (sort_key(detail, sort), detail.matchup.id))
+                    .collect_vec();
+
+                let mut sorted = expected.clone();
+                sorted.sort();
+                if descending {
+                    sorted.reverse();
+                }
+
+                assert_eq!(paged, expected, "{sort:?} descending: {descending}");
+                assert_eq!(expected, sorted, "{sort:?} descending: {descending}");
+
+                // plenty of games tie on the sort key, which the id has to break
+                assert!(expected.iter().map(|(key, _)| key).duplicates().count() > 0);
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn a_full_last_page_has_no_cursor() {
+        let ctx = testing::new_game(4).await;
+        testing::play_weeks(&ctx, 2).await;
+
+        let team_id = TeamInfo::get_all(&ctx).await.unwrap()[0].id;
+        let search = MatchupSearch {
+            team_id: Some(team_id),
+            sort: MatchupSort::Margin,
+            descending: true,
+            ..Default::default()
+        };
+
+        let total = Matchup::search(&ctx, &search).await.unwrap().matchups.len() as u32;
+
+        let whole = Matchup::search(
+            &ctx,
+            &MatchupSearch {
+                limit: Some(total),
+                ..search.clone()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(whole.matchups.len() as u32, total);
+        assert!(whole.next_cursor.is_none());
+
+        let pages = all_pages(
+            &ctx,
+            MatchupSearch {
+                limit: Some(total - 1),
+                ..search.clone()
+            },
+        )
+        .await;
+
+        assert_eq!(
+            pages
+                .iter()
+                .map(|page| page.matchups.len() as u32)
+                .collect_vec(),
+            [total - 1, 1]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn bad_cursors_are_invalid_arguments() {
+        assert_eq!(decode_cursor(&encode_cursor(-1, 42)).unwrap(), (-1, 42));
+
+        for cursor in ["", "nope", "1", "1:", ":2", "a:2", "1:b", "1:2:3"] {
+            assert!(
+                matches!(decode_cursor(cursor), Err(OakenError::InvalidArgument(_))),
+                "{cursor:?}"
+            );
+        }
+
+        let dir = std::env::temp_dir();
+        let ctx = OakenCtx::in_memory(Paths::new(&dir, &dir)).await.unwrap();
+        let search = MatchupSearch {
+            cursor: Some("nope".to_owned()),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            Matchup::search(&ctx, &search).await,
+            Err(OakenError::InvalidArgument(_))
+        ));
     }
 }
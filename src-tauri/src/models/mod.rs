@@ -1,7 +1,14 @@
+pub mod career;
+pub mod coach;
+pub mod finance;
 pub mod game;
+pub mod head_to_head;
 pub mod league;
 pub mod matchup;
+pub mod records;
 pub mod schedule;
+pub mod season;
 pub mod standings;
 pub mod team;
+pub mod team_history;
 pub mod tier;
@@ -0,0 +1,416 @@
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use sqlx::FromRow;
+use tap::Pipe;
+
+use crate::{ctx::OakenCtx, error::OakenResult, sql_args};
+
+use super::{
+    matchup::{Matchup, Outcome},
+    season::SeasonChampion,
+    standings::Standing,
+};
+
+/// Something the record book keeps the best ever of
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Debug, PartialEq, Eq, Hash, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum RecordKind {
+    /// Widest margin of victory in a single game
+    BiggestWin,
+    /// Most points scored between both sides of a single game
+    HighestScoringMatch,
+    LongestWinStreak,
+    /// Most games in a row without a defeat
+    LongestUnbeatenRun,
+    LongestLosingStreak,
+    /// Most table points taken in a single season
+    MostSeasonPoints,
+    MostTitles,
+}
+
+/// Which part of the world a record is the best of
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecordScope {
+    World,
+    League { id: i32 },
+    Team { id: i32 },
+}
+
+impl RecordScope {
+    /// The `scope` and `scope_id` this is stored as
+    fn columns(&self) -> (&'static str, i32) {
+        match self {
+            Self::World => ("world", 0),
+            Self::League { id } => ("league", *id),
+            Self::Team { id } => ("team", *id),
+        }
+    }
+
+    fn from_columns(scope: &str, id: i32) -> Self {
+        match scope {
+            "league" => Self::League { id },
+            "team" => Self::Team { id },
+            _ => Self::World,
+        }
+    }
+}
+
+#[derive(Serialize, Type, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Record {
+    pub kind: RecordKind,
+    pub scope: RecordScope,
+    pub value: i32,
+    /// The team holding the record, unless it belongs to a game rather than a team
+    pub team_id: Option<i32>,
+    /// The game the record was set in, for records set in a single game
+    pub matchup_id: Option<i32>,
+    /// When the record was set, or last extended for a streak
+    pub year: i32,
+    pub wk_no: i32,
+}
+
+#[derive(FromRow)]
+struct RecordRow {
+    kind: RecordKind,
+    scope: String,
+    scope_id: i32,
+    value: i32,
+    team_id: Option<i32>,
+    matchup_id: Option<i32>,
+    year: i32,
+    wk_no: i32,
+}
+
+impl From<RecordRow> for Record {
+    fn from(row: RecordRow) -> Self {
+        Self {
+            kind: row.kind,
+            scope: RecordScope::from_columns(&row.scope, row.scope_id),
+            value: row.value,
+            team_id: row.team_id,
+            matchup_id: row.matchup_id,
+            year: row.year,
+            wk_no: row.wk_no,
+        }
+    }
+}
+
+/// The run of results a team is on, carried over from one season into the next
+#[derive(FromRow, Clone, Copy, Default, PartialEq)]
+struct TeamRun {
+    team_id: i32,
+    wins: i32,
+    unbeaten: i32,
+    losses: i32,
+}
+
+impl TeamRun {
+    fn extend(self, outcome: Outcome) -> Self {
+        Self {
+            wins: if outcome == Outcome::Win {
+                self.wins + 1
+            } else {
+                0
+            },
+            unbeaten: if outcome == Outcome::Loss {
+                0
+            } else {
+                self.unbeaten + 1
+            },
+            losses: if outcome == Outcome::Loss {
+                self.losses + 1
+            } else {
+                0
+            },
+            ..self
+        }
+    }
+}
+
+/// Every record in the book, read in to be checked against a week's results and written back
+/// once they have all been offered
+struct RecordBook {
+    records: HashMap<(RecordKind, RecordScope), Record>,
+    /// Records that were already in the book when it was read in
+    existing: HashSet<(RecordKind, RecordScope)>,
+    changed: HashSet<(RecordKind, RecordScope)>,
+}
+
+impl RecordBook {
+    async fn load(ctx: &OakenCtx) -> OakenResult<Self> {
+        let records: HashMap<_, _> = ctx
+            .pool()?
+            .query::<RecordRow>("SELECT * FROM records;")
+            .await?
+            .into_iter()
+            .map(Record::from)
+            .map(|record| ((record.kind, record.scope), record))
+            .collect();
+
+        Ok(Self {
+            existing: records.keys().copied().collect(),
+            records,
+            changed: HashSet::new(),
+        })
+    }
+
+    /// Sets `record` in its own scope, its league's and the world's, wherever it beats the one
+    /// already there. The record for the team is kept for every team in `team_ids`
+    fn offer(&mut self, record: Record, league_id: i32, team_ids: &[i32]) {
+        let scopes = [RecordScope::World, RecordScope::League { id: league_id }]
+            .into_iter()
+            .chain(team_ids.iter().map(|id| RecordScope::Team { id: *id }));
+
+        for scope in scopes {
+            let key = (record.kind, scope);
+
+            // equalling a record isn't enough to take it
+            if self
+                .records
+                .get(&key)
+                .is_some_and(|current| current.value >= record.value)
+            {
+                continue;
+            }
+
+            self.records.insert(
+                key,
+                Record {
+                    scope,
+                    ..record.clone()
+                },
+            );
+            self.changed.insert(key);
+        }
+    }
+
+    /// Writes every record that changed, returning those that beat one from before the book was
+    /// read in
+    fn save(self, ctx: &OakenCtx) -> OakenResult<Vec<Record>> {
+        for key in &self.changed {
+            let record = &self.records[key];
+            let (scope, scope_id) = record.scope.columns();
+
+            ctx.pool()?.exec_with(
+                r#"
+                INSERT INTO records (kind, scope, scope_id, value, team_id, matchup_id, year, wk_no)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (kind, scope, scope_id) DO UPDATE SET
+                    value = excluded.value,
+                    team_id = excluded.team_id,
+                    matchup_id = excluded.matchup_id,
+                    year = excluded.year,
+                    wk_no = excluded.wk_no;
+            "#,
+                sql_args![
+                    record.kind,
+                    scope,
+                    scope_id,
+                    record.value,
+                    record.team_id,
+                    record.matchup_id,
+                    record.year,
+                    record.wk_no,
+                ],
+            )?;
+        }
+
+        Ok(self
+            .changed
+            .intersection(&self.existing)
+            .map(|key| self.records[key].clone())
+            .sorted_by_key(|record| (record.kind as i32, record.scope.columns()))
+            .collect_vec())
+    }
+}
+
+impl Record {
+    /// Every record held in `scope`
+    pub async fn get(ctx: &OakenCtx, scope: &RecordScope) -> OakenResult<Vec<Self>> {
+        let (scope, scope_id) = scope.columns();
+
+        ctx.pool()?
+            .query_with::<RecordRow>(
+                "SELECT * FROM records WHERE scope = $1 AND scope_id = $2;",
+                sql_args![scope, scope_id],
+            )
+            .await?
+            .into_iter()
+            .map(Record::from)
+            .sorted_by_key(|record| record.kind as i32)
+            .collect_vec()
+            .pipe(Ok)
+    }
+
+    /// Checks `results`, the games of week `wk_no` of `year`, against the record book once the
+    /// table has been updated for them. Returns the records they broke
+    pub async fn record_week(
+        ctx: &OakenCtx, year: &i32, wk_no: &i32, results: &[Matchup],
+    ) -> OakenResult<Vec<Self>> {
+        let mut book = RecordBook::load(ctx).await?;
+
+        let standings: HashMap<i32, Standing> = Standing::get(ctx, year)
+            .await?
+            .into_iter()
+            .map(|standing| (standing.team_id, standing))
+            .collect();
+
+        let mut runs: HashMap<i32, TeamRun> = ctx
+            .pool()?
+            .query::<TeamRun>("SELECT * FROM team_runs;")
+            .await?
+            .into_iter()
+            .map(|run| (run.team_id, run))
+            .collect();
+
+        let record = |kind, value, team_id, matchup_id| Record {
+            kind,
+            scope: RecordScope::World,
+            value,
+            team_id,
+            matchup_id,
+            year: *year,
+            wk_no: *wk_no,
+        };
+
+        for matchup in results.iter().sorted_by_key(|matchup| matchup.id) {
+            let (Some(home_score), Some(away_score)) =
+                (matchup.home_team_score, matchup.away_team_score)
+            else {
+                continue;
+            };
+
+            let sides = [matchup.home_team_id, matchup.away_team_id];
+
+            // a game between teams without a row in the table can't be placed in a league
+            let Some(league_id) = sides
+                .iter()
+                .find_map(|team_id| standings.get(team_id))
+                .map(|standing| standing.league_id)
+            else {
+                continue;
+            };
+
+            book.offer(
+                record(
+                    RecordKind::HighestScoringMatch,
+                    home_score + away_score,
+                    None,
+                    Some(matchup.id),
+                ),
+                league_id,
+                &sides,
+            );
+
+            for team_id in sides {
+                let Some(outcome) = matchup.outcome_for(&team_id) else {
+                    continue;
+                };
+
+                if outcome == Outcome::Win {
+                    book.offer(
+                        record(
+                            RecordKind::BiggestWin,
+                            (home_score - away_score).abs(),
+                            Some(team_id),
+                            Some(matchup.id),
+                        ),
+                        league_id,
+                        &[team_id],
+                    );
+                }
+
+                let run = runs
+                    .get(&team_id)
+                    .copied()
+                    .unwrap_or(TeamRun {
+                        team_id,
+                        ..Default::default()
+                    })
+                    .extend(outcome);
+                runs.insert(team_id, run);
+
+                for (kind, len) in [
+                    (RecordKind::LongestWinStreak, run.wins),
+                    (RecordKind::LongestUnbeatenRun, run.unbeaten),
+                    (RecordKind::LongestLosingStreak, run.losses),
+                ] {
+                    if len > 0 {
+                        book.offer(
+                            record(kind, len, Some(team_id), None),
+                            league_id,
+                            &[team_id],
+                        );
+                    }
+                }
+
+                if let Some(standing) = standings.get(&team_id) {
+                    book.offer(
+                        record(
+                            RecordKind::MostSeasonPoints,
+                            standing.points,
+                            Some(team_id),
+                            None,
+                        ),
+                        league_id,
+                        &[team_id],
+                    );
+                }
+
+                ctx.pool()?.exec_with(
+                    r#"
+                    INSERT INTO team_runs (team_id, wins, unbeaten, losses)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT (team_id) DO UPDATE SET
+                        wins = excluded.wins,
+                        unbeaten = excluded.unbeaten,
+                        losses = excluded.losses;
+                "#,
+                    sql_args![team_id, run.wins, run.unbeaten, run.losses],
+                )?;
+            }
+        }
+
+        book.save(ctx)
+    }
+
+    /// Adds this season's `champions` to the record book, in week `wk_no` of `year`. Returns the
+    /// records they broke
+    pub async fn record_titles(
+        ctx: &OakenCtx, year: &i32, wk_no: &i32, champions: &[SeasonChampion],
+    ) -> OakenResult<Vec<Self>> {
+        let mut book = RecordBook::load(ctx).await?;
+
+        for champion in champions.iter().sorted_by_key(|champion| champion.tier_id) {
+            let (titles,): (i32,) = ctx
+                .pool()?
+                .query_one_with(
+                    "SELECT COUNT(*) FROM season_champions WHERE team_id = $1;",
+                    sql_args![champion.team_id],
+                )
+                .await?;
+
+            book.offer(
+                Record {
+                    kind: RecordKind::MostTitles,
+                    scope: RecordScope::World,
+                    value: titles,
+                    team_id: Some(champion.team_id),
+                    matchup_id: None,
+                    year: *year,
+                    wk_no: *wk_no,
+                },
+                champion.league_id,
+                &[champion.team_id],
+            );
+        }
+
+        book.save(ctx)
+    }
+}
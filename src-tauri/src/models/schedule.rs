@@ -1,14 +1,16 @@
-
-
-use ::futures::future::join_all;
+use ::futures::future::try_join_all;
 use itertools::{Itertools};
 use serde::{Deserialize, Serialize};
 use specta::Type;
 use tap::Pipe;
 
-use crate::{shared::pool::get_pool, sql_args};
+use crate::{
+    ctx::OakenCtx,
+    error::{OakenError, OakenResult},
+    sql_args,
+};
 
-use super::{matchup::Matchup, team::Team};
+use super::{matchup::Matchup, season::Season, team::Team};
 
 #[derive(Serialize, Deserialize, Type, Clone, Debug)]
 pub struct Schedule {
@@ -28,35 +30,35 @@ pub struct ScheduleRow {
 }
 
 impl Schedule {
-    pub async fn create_empty(year: i32, tier_id: i32, league_id: i32) -> Self {
-        let pool = get_pool();
+    pub async fn create_empty(
+        ctx: &OakenCtx, year: i32, tier_id: i32, league_id: i32,
+    ) -> OakenResult<Self> {
+        let pool = ctx.pool()?;
         let row: ScheduleRow = pool
-            .query_with(
+            .query_one_with(
                 r#"
             INSERT INTO schedules (year, tier_id, league_id) VALUES ($1, $2, $3);
             SELECT * FROM schedules WHERE id = last_insert_rowid();
             "#,
                 sql_args![year, tier_id, league_id],
             )
-            .await
-            .into_iter()
-            .nth(0)
-            .unwrap();
+            .await?;
 
-        Self {
+        Ok(Self {
             year: row.year,
             league_id: row.league_id,
             tier_id: row.tier_id,
             id: row.id,
             matchups: vec![],
-        }
+        })
     }
 
-    pub async fn get_all() -> Vec<Self> {
-        let pool = get_pool();
-        let rows: Vec<ScheduleRow> = pool.query("SELECT * FROM schedules;").await;
+    pub async fn get_all(ctx: &OakenCtx) -> OakenResult<Vec<Self>> {
+        let pool = ctx.pool()?;
+        let pool = &pool;
+        let rows: Vec<ScheduleRow> = pool.query("SELECT * FROM schedules;").await?;
 
-        join_all(rows.into_iter().map(
+        try_join_all(rows.into_iter().map(
             |ScheduleRow {
                  id,
                  year,
@@ -68,65 +70,69 @@ impl Schedule {
                         "SELECT * FROM matchups WHERE schedule_id = $1;",
                         sql_args![id],
                     )
-                    .await;
+                    .await?;
 
-                Schedule {
+                Ok::<_, OakenError>(Schedule {
                     matchups,
                     year,
                     id,
                     league_id,
                     tier_id,
-                }
+                })
             },
         ))
         .await
     }
 
-    pub async fn get_all_by_year(year: &i32) -> Vec<Self> {
-        let pool = get_pool();
+    pub async fn get_all_by_year(ctx: &OakenCtx, year: &i32) -> OakenResult<Vec<Self>> {
+        let pool = ctx.pool()?;
         let schedule_rows: Vec<ScheduleRow> = pool
             .query_with("SELECT * FROM schedules WHERE year = $1", sql_args![year])
-            .await;
+            .await?;
 
         schedule_rows
             .into_iter()
             .map(|row| async move {
-                Schedule {
+                Ok::<_, OakenError>(Schedule {
                     id: row.id,
                     league_id: row.league_id,
                     tier_id: row.tier_id,
                     year: row.year,
-                    matchups: Matchup::get_all_for_schedule(row.id).await,
-                }
+                    matchups: Matchup::get_all_for_schedule(ctx, row.id).await?,
+                })
             })
-            .pipe(|it| join_all(it))
+            .pipe(try_join_all)
             .await
     }
 
-    pub async fn create_round_robin(league_id: i32, tier_id: i32, year: i32) -> Self {
-        let team_ids = Team::get_by_division(league_id, tier_id)
-            .await
+    pub async fn create_round_robin(
+        ctx: &OakenCtx, league_id: i32, tier_id: i32, season: &Season,
+    ) -> OakenResult<Self> {
+        let team_ids = Team::get_by_division(ctx, league_id, tier_id)
+            .await?
             .into_iter()
             .map(|team| team.id)
             .collect_vec();
         let num_teams = team_ids.len();
 
         if num_teams % 2 != 0 {
-            panic!("team count is not even");
+            return Err(OakenError::Config(format!(
+                "tier {tier_id} of league {league_id} has an odd number of teams ({num_teams})"
+            )));
         }
 
-        let mut schedule = Schedule::create_empty(year, tier_id, league_id).await;
+        let mut schedule = Schedule::create_empty(ctx, season.year, tier_id, league_id).await?;
 
         let wks = Scheduler::round_robin(team_ids);
 
         for wk in wks {
             for ScheduledMatch { home_id, away_id } in wk.matches {
-                let matchup = Matchup::create(home_id, away_id, wk.wk_no, 0, schedule.id).await;
+                let matchup = Matchup::create(ctx, home_id, away_id, wk.wk_no, season.id, schedule.id).await?;
                 schedule.matchups.push(matchup);
             }
         }
 
-        return schedule;
+        return Ok(schedule);
     }
 }
 
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::{
+    ctx::OakenCtx,
+    error::{OakenError, OakenResult},
+    sql_args,
+};
+
+use super::standings::Standing;
+
+/// Where a season is up to
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(rename_all = "snake_case")]
+pub enum SeasonPhase {
+    /// Fixtures are out but none have been played
+    Preseason,
+    Regular,
+    /// Reserved for knockout fixtures after the regular season
+    Playoffs,
+    /// Every fixture has been played and the champions are decided
+    Offseason,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Season {
+    pub id: i32,
+    pub year: i32,
+    pub phase: SeasonPhase,
+    pub start_wk: i32,
+    /// The last week with fixtures in it
+    pub end_wk: i32,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Debug, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct SeasonChampion {
+    pub season_id: i32,
+    pub league_id: i32,
+    pub tier_id: i32,
+    pub team_id: i32,
+}
+
+impl Season {
+    pub async fn create(ctx: &OakenCtx, year: i32) -> OakenResult<Self> {
+        ctx.pool()?
+            .query_one_with(
+                r#"
+                INSERT INTO seasons (year) VALUES ($1);
+                SELECT * FROM seasons WHERE id = last_insert_rowid();
+            "#,
+                sql_args![year],
+            )
+            .await
+    }
+
+    pub async fn get_all(ctx: &OakenCtx) -> OakenResult<Vec<Self>> {
+        ctx.pool()?
+            .query("SELECT * FROM seasons ORDER BY year;")
+            .await
+    }
+
+    pub async fn get_by_year(ctx: &OakenCtx, year: &i32) -> OakenResult<Self> {
+        ctx.pool()?
+            .query_with("SELECT * FROM seasons WHERE year = $1;", sql_args![year])
+            .await?
+            .into_iter()
+            .nth(0)
+            .ok_or_else(|| OakenError::not_found("season", year))
+    }
+
+    /// Works out the last week of the season from its fixtures, once they have all been made
+    pub async fn update_end_wk(&mut self, ctx: &OakenCtx) -> OakenResult<()> {
+        let pool = ctx.pool()?;
+
+        pool.exec_with(
+            r#"
+            UPDATE seasons
+            SET end_wk = (SELECT COALESCE(MAX(wk_no), 0) FROM matchups WHERE season_id = $1)
+            WHERE id = $1;
+        "#,
+            sql_args![self.id],
+        )?;
+
+        *self = pool
+            .query_one_with("SELECT * FROM seasons WHERE id = $1;", sql_args![self.id])
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_phase(&mut self, ctx: &OakenCtx, phase: SeasonPhase) -> OakenResult<()> {
+        ctx.pool()?.exec_with(
+            "UPDATE seasons SET phase = $2 WHERE id = $1;",
+            sql_args![self.id, phase],
+        )?;
+
+        self.phase = phase;
+
+        Ok(())
+    }
+
+    /// Records the top team of each of `tables` as that tier's champion for the season
+    pub fn crown_champions<'a>(
+        &self, ctx: &OakenCtx, tables: impl IntoIterator<Item = &'a Vec<Standing>>,
+    ) -> OakenResult<()> {
+        for winner in tables.into_iter().filter_map(|table| table.first()) {
+            ctx.pool()?.exec_with(
+                r#"
+                INSERT INTO season_champions (season_id, league_id, tier_id, team_id)
+                VALUES ($1, $2, $3, $4);
+            "#,
+                sql_args![self.id, winner.league_id, winner.tier_id, winner.team_id],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_champions(&self, ctx: &OakenCtx) -> OakenResult<Vec<SeasonChampion>> {
+        ctx.pool()?
+            .query_with(
+                "SELECT * FROM season_champions WHERE season_id = $1 ORDER BY league_id, tier_id;",
+                sql_args![self.id],
+            )
+            .await
+    }
+}
@@ -1,13 +1,33 @@
-use futures::future::join_all;
+use std::collections::HashMap;
+
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use specta::Type;
 use sqlx::prelude::*;
 
-use crate::{models::team::Team, shared::pool::get_pool};
+use crate::{
+    ctx::OakenCtx,
+    error::{OakenError, OakenResult},
+    sql_args,
+};
+
+use super::matchup::Outcome;
+
+/// Table points for a win and a draw. Two for a win keeps the table in the same order as ranking
+/// by wins less losses
+const WIN_POINTS: i32 = 2;
+const DRAW_POINTS: i32 = 1;
 
-use super::tier::Tier;
+/// Table points a team takes from a game ending in `outcome`
+pub fn table_points(outcome: Outcome) -> i32 {
+    match outcome {
+        Outcome::Win => WIN_POINTS,
+        Outcome::Draw => DRAW_POINTS,
+        Outcome::Loss => 0,
+    }
+}
 
-#[derive(Serialize, Deserialize, Type)]
+#[derive(Serialize, Deserialize, Type, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Standing {
     pub team_id: i32,
@@ -20,6 +40,10 @@ pub struct Standing {
     pub points_for: i32,
     pub points_against: i32,
     pub streak: i32,
+    pub points: i32,
+    /// Position in the team's tier, from 1
+    pub rank: i32,
+    /// Share of games won, counting a draw as half a win. Teams yet to play have none
     pub win_percent: Option<f64>,
 }
 
@@ -27,55 +51,557 @@ pub struct Standing {
 struct StandingRow {
     team_id: i32,
     team_name: String,
+    league_id: i32,
+    tier_id: i32,
     wins: i32,
     draws: i32,
     losses: i32,
     points_for: i32,
     points_against: i32,
+    streak: i32,
+    points: i32,
+    rank: i32,
+}
+
+impl From<StandingRow> for Standing {
+    fn from(row: StandingRow) -> Self {
+        let played = row.wins + row.draws + row.losses;
+
+        Standing {
+            team_id: row.team_id,
+            tier_id: row.tier_id,
+            league_id: row.league_id,
+            points_for: row.points_for,
+            points_against: row.points_against,
+            team_name: row.team_name,
+            wins: row.wins,
+            draws: row.draws,
+            losses: row.losses,
+            streak: row.streak,
+            points: row.points,
+            rank: row.rank,
+            win_percent: (played > 0)
+                .then(|| ((row.wins as f64) + (0.5 * row.draws as f64)) / played as f64),
+        }
+    }
 }
 
+/// Where a team stood once a week had been played, as given by [`Standing::get_weekly`]
+#[derive(FromRow)]
+pub struct WeeklyStanding {
+    pub wk_no: i32,
+    pub points: i32,
+    pub rank: i32,
+}
+
+/// Ranks the teams with a row in the table for year `$1`, as `ranked (wk_no, team_id, points,
+/// rank)`, with `$2` and `$3` table points for a win and a draw. Only the tier `$4` is ranked unless
+/// it's NULL. The table is ranked as it stood after each week played if `$5` is true, or else just
+/// as it stands now.
+///
+/// Teams level on points are split by the points they took off each other, then by their points
+/// difference and points scored
+const RANKING: &str = r#"
+    WITH results AS (
+        SELECT
+            matchups.wk_no,
+            matchups.home_team_id AS team_id,
+            matchups.away_team_id AS opponent_id,
+            matchups.home_team_score AS scored,
+            matchups.away_team_score AS conceded
+        FROM matchups
+        INNER JOIN schedules ON schedules.id = matchups.schedule_id
+        WHERE schedules.year = $1
+            AND ($4 IS NULL OR schedules.tier_id = $4)
+            AND matchups.home_team_score IS NOT NULL
+        UNION ALL
+        SELECT
+            matchups.wk_no,
+            matchups.away_team_id,
+            matchups.home_team_id,
+            matchups.away_team_score,
+            matchups.home_team_score
+        FROM matchups
+        INNER JOIN schedules ON schedules.id = matchups.schedule_id
+        WHERE schedules.year = $1
+            AND ($4 IS NULL OR schedules.tier_id = $4)
+            AND matchups.home_team_score IS NOT NULL
+    ),
+    -- the weeks to rank the table after, where week 0 is before a ball has been kicked
+    cutoffs AS (
+        SELECT DISTINCT wk_no FROM results WHERE $5
+        UNION
+        SELECT COALESCE(MAX(wk_no), 0) FROM results
+    ),
+    tallies AS (
+        SELECT
+            cutoffs.wk_no AS cutoff,
+            standings.team_id,
+            standings.tier_id,
+            COALESCE(SUM($2 * (results.scored > results.conceded) + $3 * (results.scored = results.conceded)), 0) AS points,
+            COALESCE(SUM(results.scored), 0) AS points_for,
+            COALESCE(SUM(results.conceded), 0) AS points_against
+        FROM cutoffs
+        CROSS JOIN standings
+        LEFT JOIN results
+            ON results.team_id = standings.team_id AND results.wk_no <= cutoffs.wk_no
+        WHERE standings.year = $1 AND ($4 IS NULL OR standings.tier_id = $4)
+        GROUP BY cutoffs.wk_no, standings.team_id, standings.tier_id
+    ),
+    -- the table points each team took from games against the teams it's level with
+    head_to_head AS (
+        SELECT
+            ours.cutoff,
+            ours.team_id,
+            SUM($2 * (results.scored > results.conceded) + $3 * (results.scored = results.conceded)) AS points
+        FROM results
+        INNER JOIN tallies AS ours
+            ON ours.team_id = results.team_id AND results.wk_no <= ours.cutoff
+        INNER JOIN tallies AS theirs
+            ON theirs.team_id = results.opponent_id AND theirs.cutoff = ours.cutoff
+        WHERE ours.tier_id = theirs.tier_id AND ours.points = theirs.points
+        GROUP BY ours.cutoff, ours.team_id
+    ),
+    ranked AS (
+        SELECT
+            tallies.cutoff AS wk_no,
+            tallies.team_id,
+            tallies.points,
+            ROW_NUMBER() OVER (
+                PARTITION BY tallies.cutoff, tallies.tier_id
+                ORDER BY
+                    tallies.points DESC,
+                    COALESCE(head_to_head.points, 0) DESC,
+                    tallies.points_for - tallies.points_against DESC,
+                    tallies.points_for DESC,
+                    tallies.team_id
+            ) AS rank
+        FROM tallies
+        LEFT JOIN head_to_head
+            ON head_to_head.cutoff = tallies.cutoff AND head_to_head.team_id = tallies.team_id
+    )
+"#;
+
 impl Standing {
-    pub async fn get(year: &i32) -> Vec<Self> {
-        let pool = get_pool();
-        let rows: Vec<StandingRow> = pool.query(r#"
-            SELECT
-                teams.id AS team_id,
-                teams.name AS team_name,
-                COUNT(CASE WHEN teams.id = matchups.home_team_id AND home_team_score > away_team_score THEN 1 END) +
-                COUNT(CASE WHEN teams.id = matchups.away_team_id AND away_team_score > home_team_score THEN 1 END) AS wins,
-                COUNT(CASE WHEN (teams.id = matchups.home_team_id OR teams.id = matchups.away_team_id) AND home_team_score = away_team_score THEN 1 END) AS draws,
-                COUNT(CASE WHEN teams.id = matchups.home_team_id AND home_team_score < away_team_score THEN 1 END) +
-                COUNT(CASE WHEN teams.id = matchups.away_team_id AND away_team_score < home_team_score THEN 1 END) AS losses,
-                SUM(CASE WHEN teams.id = matchups.home_team_id THEN home_team_score ELSE away_team_score END) AS points_for,
-                SUM(CASE WHEN teams.id = matchups.home_team_id THEN away_team_score ELSE home_team_score END) AS points_against
-            FROM teams
-            LEFT JOIN matchups
-                ON teams.id = matchups.home_team_id OR teams.id = matchups.away_team_id
-            GROUP BY teams.id, teams.name
-            ORDER BY wins - losses DESC;
-        "#).await;
-
-        join_all(rows.into_iter().map(|row| async move {
-            let Team { tier_id, .. } = Team::get(&row.team_id).await;
-            let Tier { league_id, .. } = Tier::get(&tier_id).await;
-
-            Standing {
-                team_id: row.team_id,
-                tier_id,
-                league_id,
-                points_for: row.points_for,
-                points_against: row.points_against,
-                team_name: row.team_name,
-                wins: row.wins,
-                draws: row.draws,
-                losses: row.losses,
-                streak: Team::get(&row.team_id).await.get_streak(year).await,
-                win_percent: Some(
-                    ((row.wins as f64) + (0.5 * row.draws as f64))
-                        / (row.wins + row.draws + row.losses) as f64,
+    pub async fn get(ctx: &OakenCtx, year: &i32) -> OakenResult<Vec<Self>> {
+        let rows: Vec<StandingRow> = ctx.pool()?
+            .query_with(
+                r#"
+                SELECT standings.*, teams.name AS team_name
+                FROM standings
+                INNER JOIN teams ON teams.id = standings.team_id
+                WHERE standings.year = $1
+                ORDER BY standings.points DESC, standings.rank, standings.team_id;
+            "#,
+                sql_args![year],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(Standing::from).collect_vec())
+    }
+
+    /// Fetches where `team_id` stands in its tier in `year`
+    pub async fn get_for_team(ctx: &OakenCtx, team_id: &i32, year: &i32) -> OakenResult<Self> {
+        ctx.pool()?
+            .query_with::<StandingRow>(
+                r#"
+                SELECT standings.*, teams.name AS team_name
+                FROM standings
+                INNER JOIN teams ON teams.id = standings.team_id
+                WHERE standings.team_id = $1 AND standings.year = $2;
+            "#,
+                sql_args![team_id, year],
+            )
+            .await?
+            .into_iter()
+            .nth(0)
+            .map(Standing::from)
+            .ok_or_else(|| OakenError::not_found("standing", format!("{year} for team {team_id}")))
+    }
+
+    /// Fetches the standings for `year`, grouped into their final table order by `tier_id`
+    pub async fn get_by_tier(ctx: &OakenCtx, year: &i32) -> OakenResult<HashMap<i32, Vec<Self>>> {
+        let tables = Self::get(ctx, year)
+            .await?
+            .into_iter()
+            .into_group_map_by(|standing| standing.tier_id);
+
+        Ok(tables)
+    }
+
+    /// Starts an empty table for every team with fixtures in `year`
+    pub async fn open_season(ctx: &OakenCtx, year: &i32) -> OakenResult<()> {
+        ctx.pool()?.exec_with(
+            r#"
+            INSERT INTO standings (team_id, year, league_id, tier_id)
+            SELECT matchups.home_team_id, schedules.year, schedules.league_id, schedules.tier_id
+            FROM matchups
+            INNER JOIN schedules ON schedules.id = matchups.schedule_id
+            WHERE schedules.year = $1
+            -- a team could be drawn away in every one of its fixtures
+            UNION
+            SELECT matchups.away_team_id, schedules.year, schedules.league_id, schedules.tier_id
+            FROM matchups
+            INNER JOIN schedules ON schedules.id = matchups.schedule_id
+            WHERE schedules.year = $1;
+        "#,
+            sql_args![year],
+        )?;
+
+        Self::rank(ctx, year)
+    }
+
+    /// Adds the results from week `wk_no` of `year` to the table. Must only be called once per
+    /// week, after every match in it has been played
+    pub async fn record_week(ctx: &OakenCtx, year: &i32, wk_no: &i32) -> OakenResult<()> {
+        ctx.pool()?.exec_with(
+            r#"
+            WITH results AS (
+                SELECT
+                    matchups.home_team_id AS team_id,
+                    matchups.home_team_score AS scored,
+                    matchups.away_team_score AS conceded
+                FROM matchups
+                INNER JOIN schedules ON schedules.id = matchups.schedule_id
+                WHERE schedules.year = $1 AND matchups.wk_no = $2
+                UNION ALL
+                SELECT
+                    matchups.away_team_id,
+                    matchups.away_team_score,
+                    matchups.home_team_score
+                FROM matchups
+                INNER JOIN schedules ON schedules.id = matchups.schedule_id
+                WHERE schedules.year = $1 AND matchups.wk_no = $2
+            )
+            UPDATE standings
+            SET
+                wins = wins + (results.scored > results.conceded),
+                draws = draws + (results.scored = results.conceded),
+                losses = losses + (results.scored < results.conceded),
+                points_for = points_for + results.scored,
+                points_against = points_against + results.conceded,
+                streak = CASE WHEN results.scored > results.conceded THEN streak + 1 ELSE 0 END,
+                points = points
+                    + $3 * (results.scored > results.conceded)
+                    + $4 * (results.scored = results.conceded)
+            FROM results
+            WHERE standings.team_id = results.team_id
+                AND standings.year = $1
+                AND results.scored IS NOT NULL
+                AND results.conceded IS NOT NULL;
+        "#,
+            sql_args![year, wk_no, WIN_POINTS, DRAW_POINTS],
+        )?;
+
+        Self::rank(ctx, year)
+    }
+
+    /// Works out every team's position in its tier for `year`
+    fn rank(ctx: &OakenCtx, year: &i32) -> OakenResult<()> {
+        ctx.pool()?.exec_with(
+            &format!(
+                r#"
+                {RANKING}
+                UPDATE standings
+                SET rank = ranked.rank
+                FROM ranked
+                WHERE standings.team_id = ranked.team_id AND standings.year = $1;
+            "#
+            ),
+            sql_args![year, WIN_POINTS, DRAW_POINTS, None::<i32>, false],
+        )
+    }
+
+    /// Where `team_id` stood in its tier in `year` after each week that has been played, ranked the
+    /// same way as the table itself
+    pub async fn get_weekly(
+        ctx: &OakenCtx, team_id: &i32, tier_id: &i32, year: &i32,
+    ) -> OakenResult<Vec<WeeklyStanding>> {
+        ctx.pool()?
+            .query_with(
+                &format!(
+                    r#"
+                    {RANKING}
+                    SELECT wk_no, points, rank
+                    FROM ranked
+                    WHERE team_id = $6
+                    ORDER BY wk_no;
+                "#
                 ),
+                sql_args![year, WIN_POINTS, DRAW_POINTS, tier_id, true, team_id],
+            )
+            .await
+    }
+
+    /// Throws away the table for `year` and works it out again from every result played. Returns
+    /// how many teams' rows had drifted from what the results say, which should always be none
+    pub async fn rebuild(ctx: &OakenCtx, year: &i32) -> OakenResult<i32> {
+        // a week played halfway through would look like drift
+        let _advancing = ctx.advance_lock.lock().await;
+
+        let before = Self::get(ctx, year).await?;
+
+        ctx.pool()?
+            .transaction(async {
+                ctx.pool()?.exec_with(
+                    "DELETE FROM standings WHERE year = $1;",
+                    sql_args![year],
+                )?;
+
+                Self::insert_totals(ctx, year)?;
+                Self::rank(ctx, year)
+            })
+            .await?;
+
+        let after = Self::get(ctx, year).await?;
+        let drifted = after
+            .iter()
+            .filter(|standing| !before.contains(standing))
+            .chain(
+                before
+                    .iter()
+                    .filter(|standing| after.iter().all(|each| each.team_id != standing.team_id)),
+            )
+            .count();
+
+        Ok(drifted as i32)
+    }
+
+    /// Totals up every result played in `year` into fresh rows of the table
+    fn insert_totals(ctx: &OakenCtx, year: &i32) -> OakenResult<()> {
+        ctx.pool()?.exec_with(
+            r#"
+            WITH results AS (
+                SELECT
+                    matchups.home_team_id AS team_id,
+                    schedules.league_id,
+                    schedules.tier_id,
+                    matchups.wk_no,
+                    matchups.home_team_score AS scored,
+                    matchups.away_team_score AS conceded
+                FROM matchups
+                INNER JOIN schedules ON schedules.id = matchups.schedule_id
+                WHERE schedules.year = $1
+                UNION ALL
+                SELECT
+                    matchups.away_team_id,
+                    schedules.league_id,
+                    schedules.tier_id,
+                    matchups.wk_no,
+                    matchups.away_team_score,
+                    matchups.home_team_score
+                FROM matchups
+                INNER JOIN schedules ON schedules.id = matchups.schedule_id
+                WHERE schedules.year = $1
+            ),
+            -- the last week each team failed to win, which is where its current streak began
+            streak_breaks AS (
+                SELECT team_id, MAX(wk_no) AS wk_no
+                FROM results
+                WHERE scored <= conceded
+                GROUP BY team_id
+            ),
+            totals AS (
+                SELECT
+                    results.team_id,
+                    results.league_id,
+                    results.tier_id,
+                    COUNT(CASE WHEN scored > conceded THEN 1 END) AS wins,
+                    COUNT(CASE WHEN scored = conceded THEN 1 END) AS draws,
+                    COUNT(CASE WHEN scored < conceded THEN 1 END) AS losses,
+                    COALESCE(SUM(scored), 0) AS points_for,
+                    COALESCE(SUM(conceded), 0) AS points_against,
+                    COUNT(CASE WHEN scored > conceded AND results.wk_no > COALESCE(streak_breaks.wk_no, 0) THEN 1 END) AS streak
+                FROM results
+                LEFT JOIN streak_breaks
+                    ON streak_breaks.team_id = results.team_id
+                GROUP BY results.team_id, results.league_id, results.tier_id
+            )
+            INSERT INTO standings (
+                team_id, year, league_id, tier_id, wins, draws, losses, points_for, points_against,
+                streak, points
+            )
+            SELECT
+                team_id, $1, league_id, tier_id, wins, draws, losses, points_for, points_against,
+                streak, $2 * wins + $3 * draws
+            FROM totals;
+        "#,
+            sql_args![year, WIN_POINTS, DRAW_POINTS],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        models::{matchup::Matchup, season::Season, team::Team},
+        shared::testing::{self, TestTier},
+    };
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn standings_match_each_teams_results() {
+        let ctx = testing::new_game(2).await;
+        testing::play_weeks(&ctx, 8).await;
+
+        let year = crate::models::game::GameState::get(&ctx)
+            .await
+            .unwrap()
+            .year;
+        let season = Season::get_by_year(&ctx, &year).await.unwrap();
+        let standings = Standing::get(&ctx, &year).await.unwrap();
+
+        assert_eq!(standings.len(), Team::get_all(&ctx).await.unwrap().len());
+
+        for standing in standings {
+            let team = Team::get(&ctx, &standing.team_id).await.unwrap();
+            let results = Matchup::get_with_teamid(&ctx, &team.id)
+                .await
+                .unwrap()
+                .into_iter()
+                .filter(|matchup| matchup.season_id == season.id)
+                .filter_map(|matchup| Some((matchup.outcome_for(&team.id)?, matchup)))
+                .collect_vec();
+
+            let count =
+                |outcome| results.iter().filter(|(each, _)| *each == outcome).count() as i32;
+            let (scored, conceded) = results
+                .iter()
+                .filter_map(|(_, matchup)| matchup.scores_for(&team.id))
+                .fold((0, 0), |(a, b), (ours, theirs)| (a + ours, b + theirs));
+
+            assert_eq!(standing.team_name, team.name);
+            assert_eq!(standing.tier_id, team.tier_id);
+            assert_eq!(standing.wins, count(Outcome::Win));
+            assert_eq!(standing.draws, count(Outcome::Draw));
+            assert_eq!(standing.losses, count(Outcome::Loss));
+            assert_eq!(
+                standing.points,
+                results
+                    .iter()
+                    .map(|(outcome, _)| table_points(*outcome))
+                    .sum::<i32>()
+            );
+            assert_eq!(
+                (standing.points_for, standing.points_against),
+                (scored, conceded)
+            );
+            assert_eq!(standing.streak, team.get_streak(&ctx, &year).await.unwrap());
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn teams_only_ever_away_get_a_row() {
+        let TestTier {
+            ctx,
+            year,
+            teams,
+            season_id,
+            schedule_id,
+            ..
+        } = testing::new_tier(&["Home", "Away"]).await;
+
+        Matchup::create(&ctx, teams[0], teams[1], 1, season_id, schedule_id)
+            .await
+            .unwrap();
+
+        Standing::open_season(&ctx, &year).await.unwrap();
+
+        let opened = Standing::get(&ctx, &year)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|standing| standing.team_id)
+            .sorted()
+            .collect_vec();
+
+        assert_eq!(opened, teams);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn head_to_head_breaks_ties_the_same_way_every_week() {
+        let TestTier {
+            ctx,
+            year,
+            tier_id,
+            teams,
+            season_id,
+            schedule_id,
+        } = testing::new_tier(&["A", "B", "C", "D"]).await;
+
+        let [a, b, c, d] = teams[..] else {
+            unreachable!()
+        };
+
+        // B and C finish level on points with C ahead on difference, but B won their meeting.
+        // A and D are level too, and split theirs, so difference decides
+        let weeks = [
+            [(a, b, 3, 1), (c, d, 2, 2)],
+            [(b, c, 1, 0), (d, a, 1, 0)],
+            [(a, c, 2, 2), (b, d, 0, 1)],
+            [(a, d, 9, 0), (c, b, 3, 3)],
+        ];
+
+        for (wk_no, games) in (1..).zip(&weeks) {
+            for (home, away, ..) in games {
+                Matchup::create(&ctx, *home, *away, wk_no, season_id, schedule_id)
+                    .await
+                    .unwrap();
             }
-        }))
-        .await
+        }
+
+        Standing::open_season(&ctx, &year).await.unwrap();
+
+        // each team's points and rank, as the table stood after each week
+        let mut tables = vec![];
+
+        for (wk_no, games) in (1..).zip(&weeks) {
+            for (home, away, home_score, away_score) in games {
+                ctx.pool()
+                    .unwrap()
+                    .exec_with(
+                        r#"
+                        UPDATE matchups SET home_team_score = $3, away_team_score = $4
+                        WHERE home_team_id = $1 AND away_team_id = $2;
+                    "#,
+                        sql_args![home, away, home_score, away_score],
+                    )
+                    .unwrap();
+            }
+
+            Standing::record_week(&ctx, &year, &wk_no).await.unwrap();
+
+            let table = Standing::get(&ctx, &year).await.unwrap();
+            tables.push(
+                table
+                    .iter()
+                    .map(|standing| (standing.team_id, (standing.points, standing.rank)))
+                    .collect::<HashMap<_, _>>(),
+            );
+        }
+
+        let order = Standing::get(&ctx, &year)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|standing| standing.team_id)
+            .collect_vec();
+
+        assert_eq!(order, [a, d, b, c]);
+
+        // ranking the table as it stood after an earlier week gives the ranks it had back then
+        for team_id in teams {
+            let weekly = Standing::get_weekly(&ctx, &team_id, &tier_id, &year)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|standing| (standing.points, standing.rank))
+                .collect_vec();
+
+            let recorded = tables.iter().map(|table| table[&team_id]).collect_vec();
+
+            assert_eq!(weekly, recorded, "team {team_id}");
+        }
     }
 }
@@ -1,4 +1,4 @@
-use futures::future::join_all;
+use std::collections::HashMap;
 
 use itertools::FoldWhile::*;
 use itertools::Itertools;
@@ -7,42 +7,103 @@ use specta::Type;
 use sqlx::{FromRow};
 use tap::Pipe;
 
-use crate::{shared::pool::get_pool, sql_args};
+use crate::{
+    conf::TeamConfigItem,
+    ctx::OakenCtx,
+    error::{OakenError, OakenResult},
+    names, sql_args,
+};
 
 use super::league::League;
-use super::matchup::Matchup;
+use super::matchup::{Matchup, Outcome};
 use super::tier::Tier;
 
+/// How many recent results make up a team's form
+const FORM_GAMES: usize = 5;
+
+/// Founding year for teams from configs written before identities were tracked
+const DEFAULT_FOUNDED: i32 = 1900;
+
 #[derive(Serialize, Type, Clone, Debug, FromRow)]
 pub struct Team {
     pub id: i32,
     pub name: String,
+    pub abbr: String,
+    pub city: String,
+    pub founded: i32,
+    pub primary_color: String,
+    pub secondary_color: String,
     pub skill: i32,
+    pub capacity: i32,
     pub tier_id: i32,
 }
 
+/// Everything that makes a team recognisable beyond its name
+#[derive(Clone, Debug)]
+pub struct TeamIdentity {
+    pub abbr: String,
+    pub city: String,
+    pub founded: i32,
+    pub primary_color: String,
+    pub secondary_color: String,
+}
+
+impl From<&TeamConfigItem> for TeamIdentity {
+    /// Fills in anything the config leaves out, working the city and abbreviation out from the
+    /// team's name
+    fn from(item: &TeamConfigItem) -> Self {
+        let city = item.city.clone().unwrap_or_else(|| {
+            item.name
+                .rsplit_once(' ')
+                .map_or(item.name.clone(), |(city, _)| city.to_owned())
+        });
+
+        Self {
+            abbr: item.abbr.clone().unwrap_or_else(|| names::abbreviate(&city)),
+            founded: item.founded.unwrap_or(DEFAULT_FOUNDED),
+            primary_color: item.primary_color.clone().unwrap_or("#1e293b".to_owned()),
+            secondary_color: item.secondary_color.clone().unwrap_or("#f8fafc".to_owned()),
+            city,
+        }
+    }
+}
+
 impl Team {
-    pub async fn create(name: String, skill: i32, tier_id: i32, league_id: i32) -> Self {
-        get_pool()
-            .query_with(
+    pub async fn create(
+        ctx: &OakenCtx, name: String, identity: &TeamIdentity, skill: i32, capacity: i32,
+        tier_id: i32, league_id: i32,
+    ) -> OakenResult<Self> {
+        ctx.pool()?
+            .query_one_with(
                 "
-                INSERT INTO teams (name, skill, tier_id, league_id) VALUES ($1, $2, $3, $4);
+                INSERT INTO teams (name, abbr, city, founded, primary_color, secondary_color, skill, capacity, tier_id, league_id)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10);
                 SELECT * FROM teams WHERE id = last_insert_rowid();
             ",
-                sql_args![name, skill, tier_id, league_id],
+                sql_args![
+                    name,
+                    &identity.abbr,
+                    &identity.city,
+                    identity.founded,
+                    &identity.primary_color,
+                    &identity.secondary_color,
+                    skill,
+                    capacity,
+                    tier_id,
+                    league_id
+                ],
             )
             .await
-            .into_iter()
-            .nth(0)
-            .unwrap()
     }
 
-    pub async fn get_all() -> Vec<Self> {
-        get_pool().query("SELECT * FROM teams;").await
+    pub async fn get_all(ctx: &OakenCtx) -> OakenResult<Vec<Self>> {
+        ctx.pool()?.query("SELECT * FROM teams;").await
     }
 
-    pub async fn get_by_division(league_id: i32, tier_id: i32) -> Vec<Team> {
-        get_pool()
+    pub async fn get_by_division(
+        ctx: &OakenCtx, league_id: i32, tier_id: i32,
+    ) -> OakenResult<Vec<Team>> {
+        ctx.pool()?
             .query_with(
                 "SELECT * FROM teams WHERE league_id = $1 AND tier_id = $2;",
                 sql_args![league_id, tier_id],
@@ -50,49 +111,100 @@ impl Team {
             .await
     }
 
-    pub async fn get(id: &i32) -> Self {
-        get_pool()
+    pub async fn get(ctx: &OakenCtx, id: &i32) -> OakenResult<Self> {
+        ctx.pool()?
             .query_with("SELECT * FROM teams WHERE id = $1;", sql_args![id])
-            .await
+            .await?
             .into_iter()
             .nth(0)
-            .unwrap()
+            .ok_or_else(|| OakenError::not_found("team", id))
     }
 
-    pub async fn get_streak(&self, year: &i32) -> i32 {
-        let mut matchups: Vec<Matchup> = get_pool()
+    pub async fn set_tier(&self, ctx: &OakenCtx, tier_id: i32) -> OakenResult<()> {
+        ctx.pool()?.exec_with(
+            "UPDATE teams SET tier_id = $2 WHERE id = $1;",
+            sql_args![self.id, tier_id],
+        )
+    }
+
+    /// The number of consecutive wins this team has going into its next match
+    pub async fn get_streak(&self, ctx: &OakenCtx, year: &i32) -> OakenResult<i32> {
+        self.get_streak_of(ctx, year, Outcome::Win).await
+    }
+
+    /// The number of consecutive results matching `outcome` this team has going into its next match
+    pub async fn get_streak_of(
+        &self, ctx: &OakenCtx, year: &i32, outcome: Outcome,
+    ) -> OakenResult<i32> {
+        let mut matchups: Vec<Matchup> = ctx.pool()?
             .query_with(
                 r#"
-                SELECT * FROM matchups
+                SELECT matchups.* FROM matchups
                 JOIN schedules ON schedules.id = matchups.schedule_id
                 WHERE schedules.year = $1 
                     AND (home_team_id = $2 OR away_team_id = $2)
                     AND (home_team_score IS NOT NULL AND away_team_score IS NOT NULL);"#,
                 sql_args![year, self.id],
             )
-            .await;
+            .await?;
 
         matchups.sort_by_key(|each| each.wk_no);
 
-        matchups
+        let streak = matchups
             .iter()
             .rev()
             .fold_while(0, |streak, matchup| {
-                if matchup.away_team_score > matchup.home_team_score
-                    && matchup.away_team_id == self.id
-                {
-                    return Continue(streak + 1);
+                if matchup.outcome_for(&self.id) == Some(outcome) {
+                    Continue(streak + 1)
+                } else {
+                    Done(streak)
                 }
+            })
+            .into_inner();
 
-                if matchup.away_team_score < matchup.home_team_score
-                    && matchup.home_team_id == self.id
-                {
-                    return Continue(streak + 1);
-                }
+        Ok(streak)
+    }
+
+    /// Share of the points available from each team's last few results in `year`, counting a
+    /// draw as half a win. Teams yet to play are left out, and are considered to be in middling
+    /// form
+    pub async fn get_forms(ctx: &OakenCtx, year: &i32) -> OakenResult<HashMap<i32, f64>> {
+        let matchups: Vec<Matchup> = ctx.pool()?
+            .query_with(
+                r#"
+                SELECT matchups.* FROM matchups
+                JOIN schedules ON schedules.id = matchups.schedule_id
+                WHERE schedules.year = $1
+                    AND (home_team_score IS NOT NULL AND away_team_score IS NOT NULL)
+                ORDER BY matchups.wk_no DESC;"#,
+                sql_args![year],
+            )
+            .await?;
 
-                return Done(streak);
+        let forms = matchups
+            .iter()
+            .flat_map(|matchup| {
+                [matchup.home_team_id, matchup.away_team_id]
+                    .map(|team_id| (team_id, matchup.outcome_for(&team_id)))
             })
-            .into_inner()
+            .into_group_map()
+            .into_iter()
+            .map(|(team_id, outcomes)| {
+                let recent = &outcomes[..outcomes.len().min(FORM_GAMES)];
+                let points: f64 = recent
+                    .iter()
+                    .map(|outcome| match outcome {
+                        Some(Outcome::Win) => 1.0,
+                        Some(Outcome::Draw) => 0.5,
+                        _ => 0.0,
+                    })
+                    .sum();
+
+                (team_id, points / recent.len() as f64)
+            })
+            .collect();
+
+        Ok(forms)
     }
 }
 
@@ -100,37 +212,152 @@ impl Team {
 pub struct TeamInfo {
     pub id: i32,
     pub name: String,
+    pub abbr: String,
+    pub city: String,
+    pub founded: i32,
+    pub primary_color: String,
+    pub secondary_color: String,
     pub skill: i32,
+    pub capacity: i32,
     pub tier: Tier,
     pub league: League,
 }
 
-impl TeamInfo {
-    pub async fn get(team_id: i32) -> TeamInfo {
-        let Team {
-            id,
-            name,
-            skill,
-            tier_id,
-        } = Team::get(&team_id).await;
-        let tier = Tier::get(&tier_id).await;
-        let league = League::get(&tier.league_id).await;
+/// A team joined onto its tier and league, as read by [`TeamInfo`]
+#[derive(FromRow)]
+struct TeamInfoRow {
+    #[sqlx(flatten)]
+    team: Team,
+    tier_name: String,
+    tier_rank: i32,
+    tier_league_id: i32,
+    league_name: String,
+    league_abbr: String,
+}
+
+impl From<TeamInfoRow> for TeamInfo {
+    fn from(row: TeamInfoRow) -> Self {
+        let TeamInfoRow {
+            team:
+                Team {
+                    id,
+                    name,
+                    abbr,
+                    city,
+                    founded,
+                    primary_color,
+                    secondary_color,
+                    skill,
+                    capacity,
+                    tier_id,
+                },
+            tier_name,
+            tier_rank,
+            tier_league_id: league_id,
+            league_name,
+            league_abbr,
+        } = row;
 
         Self {
             id,
-            league,
             name,
+            abbr,
+            city,
+            founded,
+            primary_color,
+            secondary_color,
             skill,
-            tier,
+            capacity,
+            tier: Tier {
+                id: tier_id,
+                name: tier_name,
+                rank: tier_rank,
+                league_id,
+            },
+            league: League {
+                id: league_id,
+                name: league_name,
+                abbr: league_abbr,
+            },
         }
     }
+}
 
-    pub async fn get_all() -> Vec<Self> {
-        Team::get_all()
-            .await
+impl TeamInfo {
+    const SELECT: &'static str = r#"
+        SELECT
+            teams.*,
+            tiers.name AS tier_name,
+            tiers.rank AS tier_rank,
+            tiers.league_id AS tier_league_id,
+            leagues.name AS league_name,
+            leagues.abbr AS league_abbr
+        FROM teams
+        INNER JOIN tiers ON tiers.id = teams.tier_id
+        INNER JOIN leagues ON leagues.id = tiers.league_id
+    "#;
+
+    pub async fn get(ctx: &OakenCtx, team_id: i32) -> OakenResult<TeamInfo> {
+        ctx.pool()?
+            .query_with::<TeamInfoRow>(
+                &format!("{} WHERE teams.id = $1;", Self::SELECT),
+                sql_args![team_id],
+            )
+            .await?
             .into_iter()
-            .map(|team| async move { Self::get(team.id).await })
-            .pipe(|it| join_all(it))
-            .await
+            .nth(0)
+            .map(TeamInfo::from)
+            .ok_or_else(|| OakenError::not_found("team", team_id))
+    }
+
+    pub async fn get_all(ctx: &OakenCtx) -> OakenResult<Vec<Self>> {
+        ctx.pool()?
+            .query::<TeamInfoRow>(&format!("{} ORDER BY teams.id;", Self::SELECT))
+            .await?
+            .into_iter()
+            .map(TeamInfo::from)
+            .collect_vec()
+            .pipe(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shared::testing;
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn team_infos_match_looking_each_team_up() {
+        let ctx = testing::new_game(1).await;
+        let infos = TeamInfo::get_all(&ctx).await.unwrap();
+
+        assert_eq!(infos.len(), Team::get_all(&ctx).await.unwrap().len());
+
+        for info in infos {
+            let team = Team::get(&ctx, &info.id).await.unwrap();
+            let tier = Tier::get(&ctx, &team.tier_id).await.unwrap();
+            let league = League::get(&ctx, &tier.league_id).await.unwrap();
+
+            let looked_up = serde_json::json!({
+                "id": team.id,
+                "name": team.name,
+                "abbr": team.abbr,
+                "city": team.city,
+                "founded": team.founded,
+                "primary_color": team.primary_color,
+                "secondary_color": team.secondary_color,
+                "skill": team.skill,
+                "capacity": team.capacity,
+                "tier": tier,
+                "league": league,
+            });
+
+            assert_eq!(serde_json::to_value(&info).unwrap(), looked_up);
+            assert_eq!(
+                serde_json::to_value(TeamInfo::get(&ctx, info.id).await.unwrap()).unwrap(),
+                looked_up
+            );
+        }
     }
 }
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+use serde::Serialize;
+use specta::Type;
+
+use crate::{
+    ctx::OakenCtx,
+    error::{OakenError, OakenResult},
+    sql_args,
+};
+
+use super::{
+    matchup::{Matchup, Outcome},
+    season::SeasonPhase,
+    standings::Standing,
+    team::Team,
+    tier::Tier,
+};
+
+/// Where a team's finish took it for the following season
+#[derive(Serialize, Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TierMove {
+    Promoted,
+    Stayed,
+    Relegated,
+}
+
+/// How a team did in one season, as listed by [`TeamHistory`]
+#[derive(Serialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamSeasonSummary {
+    pub year: i32,
+    pub tier: Tier,
+    /// Position in the tier, which is only final once the season is `finished`
+    pub rank: i32,
+    pub points: i32,
+    pub finished: bool,
+    /// Whether the team won its tier
+    pub champion: bool,
+    /// None until the season is over
+    pub tier_move: Option<TierMove>,
+}
+
+/// A season's row for a team, joined onto its tier, its title if it won one and the tier it
+/// played in the year after
+#[derive(sqlx::FromRow)]
+struct TeamSeasonRow {
+    year: i32,
+    league_id: i32,
+    tier_id: i32,
+    tier_name: String,
+    tier_rank: i32,
+    rank: i32,
+    points: i32,
+    phase: SeasonPhase,
+    champion: bool,
+    next_tier_rank: Option<i32>,
+}
+
+impl From<TeamSeasonRow> for TeamSeasonSummary {
+    fn from(row: TeamSeasonRow) -> Self {
+        let finished = row.phase == SeasonPhase::Offseason;
+
+        // a lower rank is a higher tier
+        let tier_move = row.next_tier_rank.filter(|_| finished).map(|next_rank| {
+            match next_rank.cmp(&row.tier_rank) {
+                std::cmp::Ordering::Less => TierMove::Promoted,
+                std::cmp::Ordering::Equal => TierMove::Stayed,
+                std::cmp::Ordering::Greater => TierMove::Relegated,
+            }
+        });
+
+        Self {
+            year: row.year,
+            tier: Tier {
+                id: row.tier_id,
+                name: row.tier_name,
+                rank: row.tier_rank,
+                league_id: row.league_id,
+            },
+            rank: row.rank,
+            points: row.points,
+            finished,
+            champion: row.champion,
+            tier_move,
+        }
+    }
+}
+
+/// Every season a team has played, oldest first, with its honours totted up
+#[derive(Serialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamHistory {
+    pub team_id: i32,
+    pub seasons: Vec<TeamSeasonSummary>,
+    pub titles: i32,
+    pub promotions: i32,
+    pub relegations: i32,
+}
+
+/// Where a team stood after one week of a season
+#[derive(Serialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamWeek {
+    pub wk_no: i32,
+    /// The team's game that week, if it had one
+    pub matchup_id: Option<i32>,
+    pub outcome: Option<Outcome>,
+    /// Table points so far
+    pub points: i32,
+    pub rank: i32,
+}
+
+/// A team's record in either its home or its away games
+#[derive(Serialize, Type, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VenueSplit {
+    pub wins: i32,
+    pub draws: i32,
+    pub losses: i32,
+    pub points_for: i32,
+    pub points_against: i32,
+}
+
+impl VenueSplit {
+    fn add(mut self, matchup: &Matchup, team_id: &i32) -> Self {
+        let (Some((ours, theirs)), Some(outcome)) =
+            (matchup.scores_for(team_id), matchup.outcome_for(team_id))
+        else {
+            return self;
+        };
+
+        match outcome {
+            Outcome::Win => self.wins += 1,
+            Outcome::Draw => self.draws += 1,
+            Outcome::Loss => self.losses += 1,
+        }
+
+        self.points_for += ours;
+        self.points_against += theirs;
+        self
+    }
+}
+
+/// A team's season in full, week by week
+#[derive(Serialize, Type, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamSeason {
+    pub team_id: i32,
+    pub year: i32,
+    pub tier: Tier,
+    /// Where the team stands, or finished if the season is over
+    pub standing: Standing,
+    pub finished: bool,
+    pub champion: bool,
+    pub tier_move: Option<TierMove>,
+    /// Places climbed in the last week played, negative if the team fell
+    pub rank_change: i32,
+    /// Every week played so far
+    pub weeks: Vec<TeamWeek>,
+    /// The team's widest win, or narrowest defeat if it has won nothing
+    pub best_result: Option<Matchup>,
+    pub worst_result: Option<Matchup>,
+    pub home: VenueSplit,
+    pub away: VenueSplit,
+}
+
+impl TeamSeason {
+    pub async fn get(ctx: &OakenCtx, team_id: i32, year: i32) -> OakenResult<Self> {
+        let summary = TeamHistory::get_rows(ctx, &team_id, Some(&year))
+            .await?
+            .into_iter()
+            .nth(0)
+            .map(TeamSeasonSummary::from)
+            .ok_or_else(|| OakenError::not_found("season", format!("{year} for team {team_id}")))?;
+
+        let standing = Standing::get_for_team(ctx, &team_id, &year).await?;
+
+        let tier_matchups: Vec<Matchup> = ctx
+            .pool()?
+            .query_with(
+                r#"
+                SELECT matchups.*
+                FROM matchups
+                INNER JOIN schedules ON schedules.id = matchups.schedule_id
+                WHERE schedules.year = $1 AND schedules.tier_id = $2
+                ORDER BY matchups.wk_no, matchups.id;
+            "#,
+                sql_args![year, summary.tier.id],
+            )
+            .await?;
+
+        let played = tier_matchups
+            .iter()
+            .filter(|matchup| matchup.home_team_score.is_some())
+            .collect_vec();
+
+        let mut standings = Standing::get_weekly(ctx, &team_id, &summary.tier.id, &year)
+            .await?
+            .into_iter()
+            .map(|standing| (standing.wk_no, standing))
+            .collect::<HashMap<_, _>>();
+
+        let weeks = played
+            .iter()
+            .group_by(|matchup| matchup.wk_no)
+            .into_iter()
+            .filter_map(|(wk_no, mut games)| {
+                let this_week = games
+                    .find(|matchup| {
+                        matchup.home_team_id == team_id || matchup.away_team_id == team_id
+                    })
+                    .copied();
+                let standing = standings.remove(&wk_no)?;
+
+                Some(TeamWeek {
+                    wk_no,
+                    matchup_id: this_week.map(|matchup| matchup.id),
+                    outcome: this_week.and_then(|matchup| matchup.outcome_for(&team_id)),
+                    points: standing.points,
+                    rank: standing.rank,
+                })
+            })
+            .collect_vec();
+
+        let rank_change = match weeks.as_slice() {
+            [.., before, last] => before.rank - last.rank,
+            _ => 0,
+        };
+
+        let ours = played
+            .iter()
+            .filter(|matchup| matchup.home_team_id == team_id || matchup.away_team_id == team_id)
+            .copied()
+            .collect_vec();
+
+        let margin = |matchup: &&&Matchup| {
+            matchup
+                .scores_for(&team_id)
+                .map_or(0, |(scored, conceded)| scored - conceded)
+        };
+
+        let home = ours
+            .iter()
+            .filter(|matchup| matchup.home_team_id == team_id)
+            .fold(VenueSplit::default(), |split, matchup| {
+                split.add(matchup, &team_id)
+            });
+        let away = ours
+            .iter()
+            .filter(|matchup| matchup.away_team_id == team_id)
+            .fold(VenueSplit::default(), |split, matchup| {
+                split.add(matchup, &team_id)
+            });
+
+        Ok(Self {
+            team_id,
+            year,
+            tier: summary.tier,
+            standing,
+            finished: summary.finished,
+            champion: summary.champion,
+            tier_move: summary.tier_move,
+            rank_change,
+            weeks,
+            best_result: ours
+                .iter()
+                .max_by_key(margin)
+                .map(|matchup| (*matchup).clone()),
+            worst_result: ours
+                .iter()
+                .min_by_key(margin)
+                .map(|matchup| (*matchup).clone()),
+            home,
+            away,
+        })
+    }
+}
+
+impl TeamHistory {
+    /// The rows for every season `team_id` played, or just the one in `year`
+    async fn get_rows(
+        ctx: &OakenCtx, team_id: &i32, year: Option<&i32>,
+    ) -> OakenResult<Vec<TeamSeasonRow>> {
+        ctx.pool()?
+            .query_with(
+                r#"
+                SELECT
+                    standings.year,
+                    standings.league_id,
+                    standings.tier_id,
+                    tiers.name AS tier_name,
+                    tiers.rank AS tier_rank,
+                    standings.rank,
+                    standings.points,
+                    seasons.phase,
+                    season_champions.team_id IS NOT NULL AS champion,
+                    next_tiers.rank AS next_tier_rank
+                FROM standings
+                INNER JOIN tiers ON tiers.id = standings.tier_id
+                INNER JOIN seasons ON seasons.year = standings.year
+                LEFT JOIN season_champions
+                    ON season_champions.season_id = seasons.id
+                    AND season_champions.team_id = standings.team_id
+                LEFT JOIN standings AS next
+                    ON next.team_id = standings.team_id AND next.year = standings.year + 1
+                LEFT JOIN tiers AS next_tiers ON next_tiers.id = next.tier_id
+                WHERE standings.team_id = $1 AND ($2 IS NULL OR standings.year = $2)
+                ORDER BY standings.year;
+            "#,
+                sql_args![team_id, year],
+            )
+            .await
+    }
+
+    pub async fn get(ctx: &OakenCtx, team_id: i32) -> OakenResult<Self> {
+        // make sure the team exists, so a typo isn't mistaken for a team with no history
+        Team::get(ctx, &team_id).await?;
+
+        let seasons = Self::get_rows(ctx, &team_id, None)
+            .await?
+            .into_iter()
+            .map(TeamSeasonSummary::from)
+            .collect_vec();
+
+        let count = |tier_move| {
+            seasons
+                .iter()
+                .filter(|season| season.tier_move == Some(tier_move))
+                .count() as i32
+        };
+
+        Ok(Self {
+            team_id,
+            titles: seasons.iter().filter(|season| season.champion).count() as i32,
+            promotions: count(TierMove::Promoted),
+            relegations: count(TierMove::Relegated),
+            seasons,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{models::game::GameState, shared::testing};
+
+    use super::*;
+
+    /// Checks that the season of every team in `tier_id` ends, so far, where the table for `year`
+    /// has it
+    async fn assert_weeks_agree(ctx: &OakenCtx, tier_id: i32, year: i32) {
+        for standing in &Standing::get_by_tier(ctx, &year).await.unwrap()[&tier_id] {
+            let season = TeamSeason::get(ctx, standing.team_id, year).await.unwrap();
+            let last = season.weeks.last().map(|week| (week.points, week.rank));
+
+            assert_eq!(
+                last,
+                Some((standing.points, standing.rank)),
+                "team {}",
+                standing.team_id
+            );
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn simulated_weeks_end_where_the_table_stands() {
+        let ctx = testing::new_game(6).await;
+        let year = GameState::get(&ctx).await.unwrap().year;
+
+        for _ in 0..6 {
+            testing::play_weeks(&ctx, 2).await;
+
+            for tier in Tier::get_all(&ctx).await.unwrap().iter().step_by(8) {
+                assert_weeks_agree(&ctx, tier.id, year).await;
+            }
+        }
+    }
+}
@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{shared::pool::get_pool, sql_args};
+use crate::{
+    ctx::OakenCtx,
+    error::{OakenError, OakenResult},
+    sql_args,
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize, specta::Type, sqlx::FromRow)]
 #[serde(rename_all = "camelCase")]
@@ -12,9 +16,11 @@ pub struct Tier {
 }
 
 impl Tier {
-    pub async fn create(name: String, rank: i32, league_id: i32) -> Self {
-        get_pool()
-            .query_with(
+    pub async fn create(
+        ctx: &OakenCtx, name: String, rank: i32, league_id: i32,
+    ) -> OakenResult<Self> {
+        ctx.pool()?
+            .query_one_with(
                 r#"
             INSERT INTO tiers (name, rank, league_id) VALUES ($1, $2, $3);
             SELECT * FROM tiers WHERE id = last_insert_rowid();
@@ -22,32 +28,30 @@ impl Tier {
                 sql_args![&name, rank, league_id],
             )
             .await
-            .into_iter()
-            .nth(0)
-            .unwrap()
     }
 
-    pub async fn get(id: &i32) -> Tier {
-        get_pool()
+    pub async fn get(ctx: &OakenCtx, id: &i32) -> OakenResult<Tier> {
+        ctx.pool()?
             .query_with("SELECT * FROM tiers WHERE id = $1", sql_args![id])
-            .await
+            .await?
             .into_iter()
             .nth(0)
-            .expect(&format!("Failed to look up tier with id: {id}"))
+            .ok_or_else(|| OakenError::not_found("tier", id))
     }
 
-    pub async fn get_by_name(name: String, league_id: i32) -> Tier {
-        get_pool()
+    pub async fn get_all(ctx: &OakenCtx) -> OakenResult<Vec<Tier>> {
+        ctx.pool()?.query("SELECT * FROM tiers;").await
+    }
+
+    pub async fn get_by_name(ctx: &OakenCtx, name: String, league_id: i32) -> OakenResult<Tier> {
+        ctx.pool()?
             .query_with(
                 "SELECT * FROM tiers WHERE league_id = $1 AND name = $2;",
                 sql_args![league_id, &name],
             )
-            .await
+            .await?
             .into_iter()
             .nth(0)
-            .expect(&format!(
-                "Failed to find tier with league_id: {} and name: {}",
-                league_id, name
-            ))
+            .ok_or_else(|| OakenError::not_found("tier", format!("{name} in league {league_id}")))
     }
 }
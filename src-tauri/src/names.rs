@@ -0,0 +1,179 @@
+use std::{collections::HashSet, fmt::Display};
+
+use itertools::Itertools;
+use rand::{seq::SliceRandom, Rng};
+use random_word::Lang;
+
+use crate::util::Capitalize;
+
+const CITY_PREFIXES: &[&str] = &[
+    "North", "South", "East", "West", "New", "Port", "Fort", "Lake", "Mount", "Old",
+];
+
+const CITY_SUFFIXES: &[&str] = &[
+    "ton", "ville", "field", "ford", "port", "burg", "wood", "dale", "haven", "view", "bridge",
+    "mouth", "stead", "ham", "by", "worth",
+];
+
+/// Word fragments that should never end up in a city name
+const BLOCKED_STEMS: &[&str] = &[
+    "anal", "anus", "bitch", "butt", "cock", "crap", "cum", "cunt", "damn", "dead", "dick", "die",
+    "dyke", "fag", "fuck", "gay", "hell", "homo", "jap", "kike", "kill", "nazi", "nig", "piss",
+    "poo", "porn", "rape", "sex", "shit", "slut", "spic", "tit", "whore",
+];
+
+const MASCOTS: &[&str] = &[
+    "Badgers", "Bears", "Bison", "Bobcats", "Bulldogs", "Cardinals", "Comets", "Cougars",
+    "Coyotes", "Cyclones", "Dragons", "Eagles", "Falcons", "Foxes", "Gators", "Grizzlies",
+    "Hawks", "Hornets", "Huskies", "Jackals", "Knights", "Lions", "Lynx", "Mariners", "Miners",
+    "Monarchs", "Mustangs", "Otters", "Owls", "Panthers", "Pilots", "Pioneers", "Ravens",
+    "Rangers", "Rebels", "Rockets", "Royals", "Sailors", "Sentinels", "Sharks", "Spartans",
+    "Stallions", "Storm", "Thunder", "Titans", "Tigers", "Vikings", "Wasps", "Wildcats",
+    "Wolves",
+];
+
+/// Colors teams are dressed in, as hex codes
+const PALETTE: &[&str] = &[
+    "#b91c1c", "#c2410c", "#b45309", "#facc15", "#15803d", "#047857", "#0f766e", "#0e7490",
+    "#1d4ed8", "#1e3a8a", "#6d28d9", "#7e22ce", "#be185d", "#111827", "#6b7280", "#f9fafb",
+];
+
+pub struct TeamName {
+    pub city: String,
+    pub mascot: String,
+    pub abbr: String,
+}
+
+impl Display for TeamName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.city, self.mascot)
+    }
+}
+
+/// Shortens a city to a three letter code, taking the initials of any leading words and filling
+/// the rest from the last, so "Fort Kenfield" becomes "FKE"
+pub fn abbreviate(city: &str) -> String {
+    let mut words = city.split_whitespace().rev();
+    let last = words.next().unwrap_or_default();
+    let initials: String = words
+        .rev()
+        .filter_map(|word| word.chars().next())
+        .take(2)
+        .collect();
+
+    let fill = 3 - initials.chars().count();
+
+    format!("{initials}{}", last.chars().take(fill).collect::<String>()).to_uppercase()
+}
+
+/// Picks a pair of distinct primary and secondary colors
+pub fn colors<R: Rng>(rng: &mut R) -> (String, String) {
+    PALETTE
+        .choose_multiple(rng, 2)
+        .map(|color| color.to_string())
+        .collect_tuple()
+        .unwrap()
+}
+
+/// How many names to draw looking for one nobody has before settling for a numbered one
+const MAX_ATTEMPTS: usize = 100;
+
+/// `name` if it isn't `taken`, otherwise the first of `numbered(2)`, `numbered(3)` and so on
+/// that isn't
+fn first_unused(
+    taken: &HashSet<String>, name: String, numbered: impl Fn(usize) -> String,
+) -> String {
+    std::iter::once(name)
+        .chain((2..).map(numbered))
+        .find(|name| !taken.contains(name))
+        .unwrap()
+}
+
+/// Builds "City Mascot" team names, never handing out the same city or abbreviation twice so that
+/// every team in the world can be told apart
+#[derive(Default)]
+pub struct NameGenerator {
+    cities: HashSet<String>,
+    abbrs: HashSet<String>,
+}
+
+impl NameGenerator {
+    pub fn next<R: Rng>(&mut self, rng: &mut R) -> TeamName {
+        let mut city = Self::city(rng);
+
+        for _ in 0..MAX_ATTEMPTS {
+            if !self.cities.contains(&city) && !self.abbrs.contains(&abbreviate(&city)) {
+                break;
+            }
+
+            city = Self::city(rng);
+        }
+
+        // there are only so many three letter codes to go round, so once they run short the
+        // names are told apart by number instead
+        let city = first_unused(&self.cities, city.clone(), |n| format!("{city} {n}"));
+        let abbr = abbreviate(&city);
+        let abbr = first_unused(&self.abbrs, abbr.clone(), |n| {
+            let n = n.to_string();
+            let kept: String = abbr.chars().take(3usize.saturating_sub(n.len())).collect();
+
+            format!("{kept}{n}")
+        });
+
+        self.cities.insert(city.clone());
+        self.abbrs.insert(abbr.clone());
+
+        TeamName {
+            city,
+            abbr,
+            mascot: MASCOTS.choose(rng).unwrap().to_string(),
+        }
+    }
+
+    fn stem<R: Rng>(rng: &mut R) -> &'static str {
+        (0..MAX_ATTEMPTS)
+            .filter_map(|_| {
+                random_word::all_len(rng.gen_range(3..=5), Lang::En)
+                    .and_then(|words| words.choose(rng))
+                    .copied()
+            })
+            .find(|stem| {
+                // plurals and past tenses make for awkward place names
+                let inflected = stem.ends_with('s') || stem.ends_with("ed");
+                let blocked = BLOCKED_STEMS.iter().any(|blocked| stem.contains(blocked));
+
+                !inflected && !blocked
+            })
+            .unwrap_or("oak")
+    }
+
+    fn city<R: Rng>(rng: &mut R) -> String {
+        let stem = Self::stem(rng);
+        let city = format!("{stem}{}", CITY_SUFFIXES.choose(rng).unwrap()).capitalize();
+
+        if rng.gen_bool(0.25) {
+            format!("{} {city}", CITY_PREFIXES.choose(rng).unwrap())
+        } else {
+            city
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn names_stay_unique_once_abbreviations_run_out() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut names = NameGenerator::default();
+
+        // far more teams than the stems give three letter codes for
+        let generated = (0..5_000).map(|_| names.next(&mut rng)).collect_vec();
+
+        assert!(generated.iter().map(|name| &name.city).all_unique());
+        assert!(generated.iter().map(|name| &name.abbr).all_unique());
+    }
+}
@@ -1,36 +1,125 @@
-use std::{path::PathBuf, str::FromStr};
-
-use tap::Pipe;
-use tauri::AppHandle;
-
-use crate::shared::APP_HNDL;
-
-static LEAGUES_CONFIG: &str = "/leagues.toml";
-static TEAM_CONFIG: &str = "/teams.toml";
-
-fn get_config_path(handle: &AppHandle, target: &'static str) -> PathBuf {
-    PathBuf::from_str(&format!(
-        "{}{target}",
-        handle
-            .path_resolver()
-            .app_config_dir()
-            .unwrap()
-            .to_str()
-            .unwrap()
-    ))
-    .unwrap()
-}
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use crate::{conf::LeagueConfig, error::OakenResult};
+
+/// The bundle identifier from `tauri.conf.json`, which names the app's own config and data dirs
+static APP_IDENTIFIER: &str = "dev.holewinski.oaken";
 
-pub fn get_team_config_path() -> PathBuf {
-    APP_HNDL
-        .get()
-        .unwrap()
-        .pipe(|handle| get_config_path(handle, TEAM_CONFIG))
+static LEAGUES_CONFIG: &str = "leagues.toml";
+static TEAM_CONFIG: &str = "teams.toml";
+static SAVES_DIR: &str = "saves";
+static SAVE_DB: &str = "db.sqlite";
+static UNDO_DIR: &str = "undo";
+
+/// Where the game keeps its world configs and saves
+#[derive(Debug)]
+pub struct Paths {
+    config_dir: PathBuf,
+    local_data_dir: PathBuf,
+    /// The save slot whose database and world config are currently in use
+    active_save: RwLock<Option<i32>>,
 }
 
-pub fn get_leagues_config_path() -> PathBuf {
-    APP_HNDL
-        .get()
-        .unwrap()
-        .pipe(|handle| get_config_path(handle, LEAGUES_CONFIG))
+impl Paths {
+    /// Default world configs are read from `config_dir`, and saves are kept in `local_data_dir`
+    pub fn new(config_dir: impl AsRef<Path>, local_data_dir: impl AsRef<Path>) -> Self {
+        Self {
+            config_dir: config_dir.as_ref().to_owned(),
+            local_data_dir: local_data_dir.as_ref().to_owned(),
+            active_save: RwLock::new(None),
+        }
+    }
+
+    /// The dirs the desktop app uses, for running against its saves from outside of it
+    pub fn for_app() -> Option<Self> {
+        Some(Self::new(
+            tauri::api::path::config_dir()?.join(APP_IDENTIFIER),
+            tauri::api::path::local_data_dir()?.join(APP_IDENTIFIER),
+        ))
+    }
+
+    /// The dir new saves copy their world config from
+    pub fn get_default_config_dir(&self) -> PathBuf {
+        self.config_dir.clone()
+    }
+
+    /// The world config new saves are copied from
+    pub fn get_default_team_config_path(&self) -> PathBuf {
+        self.config_dir.join(TEAM_CONFIG)
+    }
+
+    /// The league setup new saves are copied from
+    pub fn get_default_leagues_config_path(&self) -> PathBuf {
+        self.config_dir.join(LEAGUES_CONFIG)
+    }
+
+    /// Writes out the built-in league setup if there isn't a default one yet
+    pub fn ensure_default_leagues_config(&self) -> OakenResult<()> {
+        let path = self.get_default_leagues_config_path();
+
+        if !path.exists() {
+            fs::create_dir_all(&self.config_dir)?;
+            fs::write(path, String::try_from(LeagueConfig::default())?)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_local_data_dir(&self) -> PathBuf {
+        self.local_data_dir.clone()
+    }
+
+    pub fn get_saves_dir(&self) -> PathBuf {
+        self.local_data_dir.join(SAVES_DIR)
+    }
+
+    pub fn get_save_dir(&self, save_id: i32) -> PathBuf {
+        self.get_saves_dir().join(save_id.to_string())
+    }
+
+    pub fn get_save_db_path(&self, save_id: i32) -> PathBuf {
+        self.get_save_dir(save_id).join(SAVE_DB)
+    }
+
+    pub fn get_active_save(&self) -> Option<i32> {
+        *self.active_save.read().unwrap()
+    }
+
+    pub fn set_active_save(&self, save_id: Option<i32>) {
+        *self.active_save.write().unwrap() = save_id;
+    }
+
+    /// Where the snapshots taken before each week is played are kept for `save_id`
+    pub fn get_save_undo_dir(&self, save_id: i32) -> PathBuf {
+        self.get_save_dir(save_id).join(UNDO_DIR)
+    }
+
+    pub fn get_save_team_config_path(&self, save_id: i32) -> PathBuf {
+        self.get_save_dir(save_id).join(TEAM_CONFIG)
+    }
+
+    pub fn get_save_leagues_config_path(&self, save_id: i32) -> PathBuf {
+        self.get_save_dir(save_id).join(LEAGUES_CONFIG)
+    }
+
+    /// Where the world config in use lives: the save's own copy, or the defaults if no save has
+    /// been loaded, as is the case for an in-memory game
+    pub fn get_config_dir(&self) -> PathBuf {
+        match self.get_active_save() {
+            Some(save_id) => self.get_save_dir(save_id),
+            None => self.config_dir.clone(),
+        }
+    }
+
+    pub fn get_team_config_path(&self) -> PathBuf {
+        self.get_config_dir().join(TEAM_CONFIG)
+    }
+
+    pub fn get_leagues_config_path(&self) -> PathBuf {
+        self.get_config_dir().join(LEAGUES_CONFIG)
+    }
 }
@@ -1,26 +1,35 @@
 use serde::Serialize;
-use serde_json::json;
 use specta::Type;
-use tap::Pipe;
-use tauri::Manager;
 
-use super::APP_HNDL;
+use crate::models::{matchup::Matchup, records::Record};
 
-#[derive(Serialize, Type)]
-#[serde(rename_all = "snake_case")]
+/// Something that happened to the game, streamed to the frontend through the `gameEvents`
+/// subscription
+#[derive(Serialize, Type, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum EmitMsg {
     GameWillRestart,
-    GameDidRestart,
-    WeekDidAdvance,
-}
-
-pub struct Emitter;
-impl Emitter {
-    pub fn emit(msg: EmitMsg) {
-        APP_HNDL
-            .get()
-            .unwrap()
-            .pipe(|handle| handle.emit_all(&serde_json::to_string(&msg).unwrap(), json!({})))
-            .expect("failed to emit");
-    }
+    /// A new game was started, beginning with the season of `year`
+    GameDidRestart { year: i32 },
+    /// The game moved on to week `wk_no` of `year`, having played `results`. A season rollover
+    /// plays no matchups
+    WeekDidAdvance {
+        year: i32,
+        #[serde(rename = "wkNo")]
+        wk_no: i32,
+        results: Vec<Matchup>,
+    },
+    /// The game went back to week `wk_no` of `year`
+    WeekDidUndo {
+        year: i32,
+        #[serde(rename = "wkNo")]
+        wk_no: i32,
+    },
+    /// The week just played, or the season just closed out, beat every one of `records`, which
+    /// are given as they now stand
+    RecordsWereBroken { records: Vec<Record> },
+    SaveDidLoad {
+        #[serde(rename = "saveId")]
+        save_id: i32,
+    },
 }
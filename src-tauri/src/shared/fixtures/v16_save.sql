@@ -0,0 +1,108 @@
+-- a save from the last release before schema migrations, two weeks into its first season
+
+-- master table
+CREATE TABLE ctrl (
+    schema_ver INTEGER NOT NULL,
+    year INTEGER NOT NULL,
+    wk_no INTEGER NOT NULL
+);
+
+CREATE TABLE leagues (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    abbr TEXT NOT NULL 
+);
+
+CREATE TABLE tiers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    rank INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    league_id INTEGER NOT NULL,
+
+    FOREIGN KEY (league_id) REFERENCES leagues (id),
+    CONSTRAINT unique_name_per_league UNIQUE (name, league_id)
+);
+
+CREATE TABLE teams (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    skill INTEGER NOT NULL,
+    league_id INTEGER NOT NULL,
+    tier_id INTEGER NOT NULL,
+
+    FOREIGN KEY (tier_id) REFERENCES tiers (id)
+);
+
+-- matches table
+CREATE TABLE matchups (
+    id INTEGER PRIMARY KEY,
+    wk_no INTEGER NOT NULL,
+    season_id INTEGER NOT NULL,
+    home_team_id INTEGER NOT NULL,
+    away_team_id INTEGER NOT NULL,
+    home_team_score INTEGER,
+    away_team_score INTEGER,
+    schedule_id INTEGER,
+
+    FOREIGN KEY (home_team_id) REFERENCES teams (id),
+    FOREIGN KEY (away_team_id) REFERENCES teams (id),
+    FOREIGN KEY (schedule_id) REFERENCES schedules (id)
+);
+
+-- schedules table
+CREATE TABLE schedules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tier_id INTEGER NOT NULL,
+    league_id INTEGER NOT NULL,
+    year INTEGER NOT NULL,
+
+    CONSTRAINT unique_year_per_tier UNIQUE (year, tier_id)
+);
+
+INSERT INTO ctrl (schema_ver, year, wk_no) VALUES (16, 2023, 3);
+
+INSERT INTO leagues (id, name, abbr) VALUES (1, 'Oaken League', 'OL');
+
+INSERT INTO tiers (id, rank, name, league_id) VALUES
+    (1, 1, 'Tier 1', 1),
+    (2, 2, 'Tier 2', 1);
+
+INSERT INTO teams (id, name, skill, league_id, tier_id) VALUES
+    (1, 'Crocworth Grizzlies', 71, 1, 1),
+    (2, 'Port Ellery Herons', 64, 1, 1),
+    (3, 'Ashby Foxes', 58, 1, 1),
+    (4, 'Marlow Comets', 49, 1, 1),
+    (5, 'Dunmore Otters', 44, 1, 2),
+    (6, 'New Halden Wasps', 39, 1, 2),
+    (7, 'Kestrel Bay Stags', 33, 1, 2),
+    (8, 'Fenwick Rooks', 27, 1, 2);
+
+INSERT INTO schedules (id, tier_id, league_id, year) VALUES
+    (1, 1, 1, 2023),
+    (2, 2, 1, 2023);
+
+INSERT INTO matchups (id, wk_no, season_id, home_team_id, away_team_id, home_team_score, away_team_score, schedule_id) VALUES
+    (1, 1, 0, 1, 2, 88, 71, 1),
+    (2, 1, 0, 3, 4, 64, 60, 1),
+    (3, 2, 0, 1, 3, 75, 75, 1),
+    (4, 2, 0, 2, 4, 52, 81, 1),
+    (5, 3, 0, 1, 4, NULL, NULL, 1),
+    (6, 3, 0, 2, 3, NULL, NULL, 1),
+    (7, 4, 0, 2, 1, NULL, NULL, 1),
+    (8, 4, 0, 4, 3, NULL, NULL, 1),
+    (9, 5, 0, 3, 1, NULL, NULL, 1),
+    (10, 5, 0, 4, 2, NULL, NULL, 1),
+    (11, 6, 0, 4, 1, NULL, NULL, 1),
+    (12, 6, 0, 3, 2, NULL, NULL, 1),
+    (13, 1, 0, 5, 6, 90, 66, 2),
+    (14, 1, 0, 7, 8, 58, 77, 2),
+    (15, 2, 0, 5, 7, 70, 69, 2),
+    (16, 2, 0, 6, 8, 61, 80, 2),
+    (17, 3, 0, 5, 8, NULL, NULL, 2),
+    (18, 3, 0, 6, 7, NULL, NULL, 2),
+    (19, 4, 0, 6, 5, NULL, NULL, 2),
+    (20, 4, 0, 8, 7, NULL, NULL, 2),
+    (21, 5, 0, 7, 5, NULL, NULL, 2),
+    (22, 5, 0, 8, 6, NULL, NULL, 2),
+    (23, 6, 0, 8, 5, NULL, NULL, 2),
+    (24, 6, 0, 7, 6, NULL, NULL, 2);
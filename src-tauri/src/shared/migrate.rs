@@ -0,0 +1,332 @@
+use std::path::{Path, PathBuf};
+
+use itertools::Itertools;
+use sqlx::SqlitePool;
+
+use crate::error::OakenResult;
+
+/// A numbered change to the schema. Migrations are applied in order, each in its own transaction,
+/// and must never be edited once released so that older saves can always be brought up to date
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    sql: &'static str,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("../migrations/0001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "finances",
+        sql: include_str!("../migrations/0002_finances.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "attendance",
+        sql: include_str!("../migrations/0003_attendance.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "coaches",
+        sql: include_str!("../migrations/0004_coaches.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "team_identity",
+        sql: include_str!("../migrations/0005_team_identity.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "career",
+        sql: include_str!("../migrations/0006_career.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "matchup_indexes",
+        sql: include_str!("../migrations/0007_matchup_indexes.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "standings",
+        sql: include_str!("../migrations/0008_standings.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "seasons",
+        sql: include_str!("../migrations/0009_seasons.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "records",
+        sql: include_str!("../migrations/0010_records.sql"),
+    },
+];
+
+/// The last schema version from before migrations were introduced. Saves on it already match the
+/// initial migration, so they can be adopted as-is
+const LEGACY_SCHEMA_VER: i32 = 16;
+
+pub const fn latest_version() -> i32 {
+    MIGRATIONS[MIGRATIONS.len() - 1].version
+}
+
+/// Brings the database at `db_path` up to the latest schema. Existing saves are backed up next to
+/// the database before anything is changed, unless it has no path to be backed up beside
+pub async fn run(pool: &SqlitePool, db_path: Option<&Path>) -> OakenResult<()> {
+    if !has_table(pool, "schema_migrations").await? {
+        adopt_legacy_save(pool, db_path).await?;
+    }
+
+    let current = applied_version(pool).await?;
+    let pending = MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > current)
+        .collect_vec();
+
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    if let (true, Some(db_path)) = (current > 0, db_path) {
+        backup(pool, db_path, current).await?;
+    }
+
+    for migration in pending {
+        apply(pool, migration).await?;
+    }
+
+    sqlx::query("UPDATE ctrl SET schema_ver = $1;")
+        .bind(latest_version())
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+async fn has_table(pool: &SqlitePool, name: &str) -> OakenResult<bool> {
+    let count: i32 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = $1;",
+    )
+    .bind(name)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count > 0)
+}
+
+async fn applied_version(pool: &SqlitePool) -> OakenResult<i32> {
+    Ok(
+        sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM schema_migrations;")
+            .fetch_one(pool)
+            .await?,
+    )
+}
+
+/// Starts tracking migrations for a database created before they existed. Saves on the last
+/// legacy schema are recorded as having had the initial migration applied, while anything older
+/// could never have been upgraded, so it is backed up and cleared out
+async fn adopt_legacy_save(pool: &SqlitePool, db_path: Option<&Path>) -> OakenResult<()> {
+    // only the version is read, since a statement cached for a whole row of `ctrl` would go stale
+    // once migrations add columns to it
+    let schema_ver: Option<i32> = sqlx::query_scalar("SELECT schema_ver FROM ctrl;")
+        .fetch_one(pool)
+        .await
+        .ok();
+
+    sqlx::query(
+        r#"
+        CREATE TABLE schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+    "#,
+    )
+    .execute(pool)
+    .await?;
+
+    match schema_ver {
+        Some(LEGACY_SCHEMA_VER) => {
+            sqlx::query(
+                r#"
+                INSERT INTO schema_migrations (version, name) VALUES ($1, $2);
+                UPDATE ctrl SET schema_ver = $1;
+            "#,
+            )
+            .bind(MIGRATIONS[0].version)
+            .bind(MIGRATIONS[0].name)
+            .execute(pool)
+            .await?;
+        }
+        Some(schema_ver) => {
+            if let Some(db_path) = db_path {
+                backup(pool, db_path, schema_ver).await?;
+            }
+
+            drop_tables(pool).await?;
+        }
+        None => drop_tables(pool).await?,
+    }
+
+    Ok(())
+}
+
+/// Drops every table other than the migration log
+async fn drop_tables(pool: &SqlitePool) -> OakenResult<()> {
+    let tables: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT name FROM sqlite_master
+        WHERE type = 'table' AND name NOT LIKE 'sqlite_%' AND name != 'schema_migrations';
+    "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for table in tables {
+        sqlx::query(&format!("DROP TABLE \"{table}\";"))
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Where the copy of a save taken before migrating away from `version` lives
+pub fn backup_path(db_path: &Path, version: i32) -> PathBuf {
+    let stem = db_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("db");
+
+    db_path.with_file_name(format!("{stem}.v{version}.bak.sqlite"))
+}
+
+async fn backup(pool: &SqlitePool, db_path: &Path, version: i32) -> OakenResult<()> {
+    let path = backup_path(db_path, version);
+
+    // VACUUM INTO refuses to overwrite, so clear out any backup left by an earlier attempt
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+
+    sqlx::query("VACUUM INTO $1;")
+        .bind(path.to_string_lossy())
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+async fn apply(pool: &SqlitePool, migration: &Migration) -> OakenResult<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(migration.sql).execute(&mut *tx).await?;
+
+    sqlx::query("INSERT INTO schema_migrations (version, name) VALUES ($1, $2);")
+        .bind(migration.version)
+        .bind(migration.name)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::sqlite::SqliteConnectOptions;
+
+    use crate::{
+        ctx::OakenCtx,
+        handlers::game::GameHandlers,
+        models::{matchup::Matchup, team::Team},
+        paths::Paths,
+        shared::testing,
+    };
+
+    use super::*;
+
+    /// A save from the last release before migrations, two weeks into its first season
+    const V16_SAVE: &str = include_str!("fixtures/v16_save.sql");
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn v16_saves_keep_their_teams_and_matchups() {
+        let dir = testing::temp_dir();
+        let db_path = dir.join("db.sqlite");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let legacy = SqlitePool::connect_with(
+            SqliteConnectOptions::new()
+                .filename(&db_path)
+                .create_if_missing(true),
+        )
+        .await
+        .unwrap();
+        sqlx::query(V16_SAVE).execute(&legacy).await.unwrap();
+        legacy.close().await;
+
+        let ctx = OakenCtx::new(Paths::new(&dir, &dir));
+        ctx.init_pool(&db_path)
+            .await
+            .expect("failed to migrate the v16 save");
+
+        assert!(backup_path(&db_path, MIGRATIONS[0].version).exists());
+
+        let teams = Team::get_all(&ctx)
+            .await
+            .unwrap()
+            .into_iter()
+            .sorted_by_key(|team| team.id)
+            .collect_vec();
+
+        assert_eq!(teams.len(), 8);
+        assert_eq!(
+            (teams[0].name.as_str(), teams[0].skill, teams[0].tier_id),
+            ("Crocworth Grizzlies", 71, 1)
+        );
+        assert_eq!(
+            (teams[0].city.as_str(), teams[0].abbr.as_str()),
+            ("Crocworth", "CRO")
+        );
+        assert_eq!(teams[5].city, "New Halden");
+
+        let matchups: Vec<Matchup> = ctx
+            .pool().unwrap()
+            .query("SELECT * FROM matchups ORDER BY id;")
+            .await
+            .unwrap();
+
+        assert_eq!(matchups.len(), 24);
+        assert_eq!(
+            matchups
+                .iter()
+                .filter(|matchup| matchup.home_team_score.is_some())
+                .count(),
+            8
+        );
+        assert_eq!(
+            (matchups[0].home_team_score, matchups[0].away_team_score),
+            (Some(88), Some(71))
+        );
+
+        // and the season carries on from the week it was left on
+        GameHandlers::next_week(&ctx)
+            .await
+            .expect("failed to play a week on the upgraded save");
+
+        let week_3: Vec<Matchup> = ctx
+            .pool().unwrap()
+            .query("SELECT * FROM matchups WHERE wk_no = 3;")
+            .await
+            .unwrap();
+
+        assert!(week_3
+            .iter()
+            .all(|matchup| matchup.home_team_score.is_some() && matchup.attendance.is_some()));
+    }
+}
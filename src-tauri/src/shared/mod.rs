@@ -1,9 +1,7 @@
-use std::sync::OnceLock;
-
-use tauri::AppHandle;
-
-pub mod pool;
+pub mod migrate;
 pub mod sql;
+pub mod undo;
 pub mod emit;
 
-pub static APP_HNDL: OnceLock<AppHandle> = OnceLock::new();
+#[cfg(test)]
+pub mod testing;
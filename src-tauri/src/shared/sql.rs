@@ -1,54 +1,139 @@
 use sqlx::{
-    sqlite::{SqliteArguments, SqliteConnectOptions, SqliteQueryResult, SqliteRow},
-    FromRow, SqlitePool,
+    sqlite::{
+        SqliteArguments, SqliteConnectOptions, SqlitePoolOptions, SqliteQueryResult, SqliteRow,
+    },
+    FromRow, Sqlite, SqlitePool, Transaction,
 };
-use std::ops::Deref;
-use tauri::App;
+use std::{future::Future, ops::Deref, path::Path, str::FromStr, sync::Arc};
+use tokio::sync::Mutex;
 
-use crate::{models::game::GameState, sql_args};
+use crate::error::{OakenError, OakenResult};
 
-pub const SCHEMA_VER: i32 = 16;
+use super::migrate;
+
+pub const SCHEMA_VER: i32 = migrate::latest_version();
+
+type SharedTransaction = Arc<Mutex<Transaction<'static, Sqlite>>>;
+
+tokio::task_local! {
+    /// The transaction opened by [`SqlitePoolWrapper::transaction`] for the current task. Every
+    /// query the task makes through the wrapper runs inside it
+    static TRANSACTION: SharedTransaction;
+}
+
+fn current_transaction() -> Option<SharedTransaction> {
+    TRANSACTION.try_with(Arc::clone).ok()
+}
 
 #[derive(Debug)]
 pub struct SqlitePoolWrapper(SqlitePool);
 
 impl SqlitePoolWrapper {
-    pub fn exec(&self, sql: &str) -> SqliteQueryResult {
+    pub fn exec(&self, sql: &str) -> OakenResult<SqliteQueryResult> {
+        let tx = current_transaction();
+
         tokio::task::block_in_place(|| {
-            tauri::async_runtime::block_on(async {
-                sqlx::query(sql).execute(self.deref()).await.unwrap()
+            tokio::runtime::Handle::current().block_on(async {
+                match tx {
+                    Some(tx) => sqlx::query(sql).execute(&mut **tx.lock().await).await,
+                    None => sqlx::query(sql).execute(self.deref()).await,
+                }
+                .map_err(OakenError::from)
             })
         })
     }
 
-    pub fn exec_with(&self, sql: &str, args: SqliteArguments) {
+    pub fn exec_with(&self, sql: &str, args: SqliteArguments) -> OakenResult<()> {
+        let tx = current_transaction();
+
         tokio::task::block_in_place(|| {
-            tauri::async_runtime::block_on(async {
-                sqlx::query_with(sql, args)
-                    .execute(self.deref())
-                    .await
-                    .unwrap()
-            });
+            tokio::runtime::Handle::current().block_on(async {
+                match tx {
+                    Some(tx) => sqlx::query_with(sql, args).execute(&mut **tx.lock().await).await,
+                    None => sqlx::query_with(sql, args).execute(self.deref()).await,
+                }
+                .map(|_| ())
+                .map_err(OakenError::from)
+            })
         })
     }
 
-    pub async fn query<'lt, Output>(&self, sql: &'lt str) -> Vec<Output>
+    pub async fn query<'lt, Output>(&self, sql: &'lt str) -> OakenResult<Vec<Output>>
     where
         Output: for<'r> FromRow<'r, SqliteRow> + Send + Unpin,
     {
-        sqlx::query_as(sql).fetch_all(&self.0).await.unwrap()
+        match current_transaction() {
+            Some(tx) => sqlx::query_as(sql).fetch_all(&mut **tx.lock().await).await,
+            None => sqlx::query_as(sql).fetch_all(&self.0).await,
+        }
+        .map_err(OakenError::from)
     }
 
     pub async fn query_with<'lt, Output>(
         &self, sql: &'lt str, args: SqliteArguments<'_>,
-    ) -> Vec<Output>
+    ) -> OakenResult<Vec<Output>>
     where
         Output: for<'r> FromRow<'r, SqliteRow> + Send + Unpin,
     {
-        sqlx::query_as_with(sql, args)
-            .fetch_all(&self.0)
-            .await
-            .unwrap()
+        match current_transaction() {
+            Some(tx) => {
+                sqlx::query_as_with(sql, args)
+                    .fetch_all(&mut **tx.lock().await)
+                    .await
+            }
+            None => sqlx::query_as_with(sql, args).fetch_all(&self.0).await,
+        }
+        .map_err(OakenError::from)
+    }
+
+    /// Like [`Self::query`], for queries that always return a row, such as reads of `ctrl`
+    pub async fn query_one<'lt, Output>(&self, sql: &'lt str) -> OakenResult<Output>
+    where
+        Output: for<'r> FromRow<'r, SqliteRow> + Send + Unpin,
+    {
+        self.query(sql)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(OakenError::Database(sqlx::Error::RowNotFound))
+    }
+
+    /// Like [`Self::query_with`], for queries that always return a row, such as an insert
+    /// followed by a read of what was inserted
+    pub async fn query_one_with<'lt, Output>(
+        &self, sql: &'lt str, args: SqliteArguments<'_>,
+    ) -> OakenResult<Output>
+    where
+        Output: for<'r> FromRow<'r, SqliteRow> + Send + Unpin,
+    {
+        self.query_with(sql, args)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(OakenError::Database(sqlx::Error::RowNotFound))
+    }
+
+    /// Runs `fut` with every query it makes through the wrapper going into a single transaction,
+    /// which is only committed if `fut` succeeds. If it fails or panics part way through, none of
+    /// its writes are kept
+    pub async fn transaction<T, F>(&self, fut: F) -> OakenResult<T>
+    where
+        F: Future<Output = OakenResult<T>>,
+    {
+        if current_transaction().is_some() {
+            return fut.await;
+        }
+
+        let tx: SharedTransaction = Arc::new(Mutex::new(self.0.begin().await?));
+        let output = TRANSACTION.scope(tx.clone(), fut).await?;
+
+        Arc::into_inner(tx)
+            .expect("transaction was still in use after its scope ended")
+            .into_inner()
+            .commit()
+            .await?;
+
+        Ok(output)
     }
 }
 
@@ -59,47 +144,37 @@ impl Deref for SqlitePoolWrapper {
     }
 }
 
-pub async fn establish_connection(app: &App) -> SqlitePoolWrapper {
-    std::fs::create_dir_all(app.path_resolver().app_local_data_dir().unwrap()).unwrap();
-    let db_path = format!(
-        "{}/db.sqlite",
-        app.path_resolver()
-            .app_local_data_dir()
-            .unwrap()
-            .to_str()
-            .unwrap()
-    );
+pub async fn establish_connection(db_path: &Path) -> OakenResult<SqlitePoolWrapper> {
+    if let Some(dir) = db_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
 
     let opts = SqliteConnectOptions::new()
         .filename(db_path)
         .create_if_missing(true);
 
-    let pool = SqlitePool::connect_with(opts).await.unwrap();
-
-    let ctrl_record: Option<GameState> = sqlx::query_as("SELECT * FROM ctrl;")
-        .fetch_one(&pool)
-        .await
-        .ok();
-
-    if ctrl_record.is_none() || ctrl_record.unwrap().schema_ver < SCHEMA_VER {
-        sqlx::query(
-            r#"
-            PRAGMA writable_schema = 1;
-            DELETE FROM sqlite_master WHERE type IN ('table', 'index', 'trigger');
-            PRAGMA writable_schema = 0;
-        "#,
-        )
-        .execute(&pool)
-        .await
-        .unwrap();
-
-        sqlx::query_with(include_str!("../creates.sql"), sql_args![SCHEMA_VER])
-            .execute(&pool)
-            .await
-            .unwrap();
-    }
+    let pool = SqlitePool::connect_with(opts).await?;
+
+    migrate::run(&pool, Some(db_path)).await?;
+
+    Ok(SqlitePoolWrapper(pool))
+}
+
+/// Opens a fresh database held entirely in memory. It is kept on a single connection, since every
+/// connection to `:memory:` would otherwise get a database of its own
+pub async fn establish_in_memory() -> OakenResult<SqlitePoolWrapper> {
+    let opts = SqliteConnectOptions::from_str("sqlite::memory:")?;
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(opts)
+        .await?;
+
+    migrate::run(&pool, None).await?;
 
-    return SqlitePoolWrapper(pool);
+    Ok(SqlitePoolWrapper(pool))
 }
 
 pub struct PoolWrapper(SqlitePool);
@@ -0,0 +1,129 @@
+//! Games for tests to play, each in a database and config dir of its own
+
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    ctx::OakenCtx,
+    handlers::game::GameHandlers,
+    models::{
+        league::League,
+        schedule::Schedule,
+        season::Season,
+        team::{Team, TeamIdentity},
+        tier::Tier,
+    },
+    paths::Paths,
+};
+
+/// A league of one tier, set up by hand so that tests can pick its fixtures and results
+pub struct TestTier {
+    pub ctx: OakenCtx,
+    pub year: i32,
+    pub tier_id: i32,
+    /// The tier's teams, in the order they were named
+    pub teams: Vec<i32>,
+    pub season_id: i32,
+    /// An empty schedule for the tier, for tests to add matchups to
+    pub schedule_id: i32,
+}
+
+/// A dir no other test uses, since tests run side by side
+pub fn temp_dir() -> PathBuf {
+    static DIRS: AtomicUsize = AtomicUsize::new(0);
+
+    std::env::temp_dir().join(format!(
+        "oaken-test-{}-{}",
+        std::process::id(),
+        DIRS.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Starts a new game in the default world, rolled from `seed`
+pub async fn new_game(seed: u64) -> OakenCtx {
+    // each game gets its own copy of the config to read
+    let dir = temp_dir();
+
+    let ctx = OakenCtx::in_memory(Paths::new(&dir, &dir))
+        .await
+        .expect("failed to open an in-memory game");
+
+    ctx.paths
+        .ensure_default_leagues_config()
+        .expect("failed to write the default leagues config");
+    ctx.reseed(seed);
+
+    GameHandlers::restart_game(&ctx)
+        .await
+        .expect("failed to start a new game");
+
+    ctx
+}
+
+/// Starts a season of 2023 for a single tier of equally matched teams named `names`, with nothing
+/// on its schedule yet
+pub async fn new_tier(names: &[&str]) -> TestTier {
+    let dir = temp_dir();
+    let ctx = OakenCtx::in_memory(Paths::new(&dir, &dir))
+        .await
+        .expect("failed to open an in-memory game");
+    let year = 2023;
+
+    let league = League::create(&ctx, "League".to_owned(), "L".to_owned())
+        .await
+        .expect("failed to create a league");
+    let tier = Tier::create(&ctx, "Tier".to_owned(), 1, league.id)
+        .await
+        .expect("failed to create a tier");
+
+    let mut teams = vec![];
+    for name in names {
+        let identity = TeamIdentity {
+            abbr: name.to_uppercase(),
+            city: name.to_string(),
+            founded: 1900,
+            primary_color: "#1e293b".to_owned(),
+            secondary_color: "#f8fafc".to_owned(),
+        };
+
+        let team = Team::create(
+            &ctx,
+            name.to_string(),
+            &identity,
+            50,
+            10_000,
+            tier.id,
+            league.id,
+        )
+        .await
+        .expect("failed to create a team");
+        teams.push(team.id);
+    }
+
+    let season = Season::create(&ctx, year)
+        .await
+        .expect("failed to create a season");
+    let schedule = Schedule::create_empty(&ctx, year, tier.id, league.id)
+        .await
+        .expect("failed to create a schedule");
+
+    TestTier {
+        ctx,
+        year,
+        tier_id: tier.id,
+        teams,
+        season_id: season.id,
+        schedule_id: schedule.id,
+    }
+}
+
+/// Advances `ctx` by `weeks` weeks
+pub async fn play_weeks(ctx: &OakenCtx, weeks: usize) {
+    for _ in 0..weeks {
+        GameHandlers::next_week(ctx)
+            .await
+            .expect("failed to play a week");
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+
+use crate::{ctx::OakenCtx, error::OakenResult};
+
+/// How many weeks back the game can be rewound
+const UNDO_DEPTH: usize = 10;
+
+/// Where a snapshot waits until the week it was taken ahead of has been played. Its name isn't a
+/// number, so it's never mistaken for one in the history
+const PENDING_SNAPSHOT: &str = "pending.sqlite";
+
+/// Snapshots of the save in use, taken just before each week is played, so that weeks can be
+/// undone one at a time
+pub struct UndoHistory;
+
+impl UndoHistory {
+    /// Snapshot numbers on disk for the save in use, oldest first
+    fn get_snapshots(ctx: &OakenCtx, save_id: i32) -> Vec<u32> {
+        let Ok(entries) = fs::read_dir(ctx.paths.get_save_undo_dir(save_id)) else {
+            return vec![];
+        };
+
+        entries
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name();
+                name.to_str()?.strip_suffix(".sqlite")?.parse::<u32>().ok()
+            })
+            .sorted()
+            .collect_vec()
+    }
+
+    /// Copies out the current state of the save, to be kept with [`Snapshot::keep`] once the
+    /// week it was taken ahead of has been played. Games that aren't in a save, such as in-memory
+    /// ones, keep no history
+    pub async fn take(ctx: &OakenCtx) -> OakenResult<Snapshot> {
+        let Some(save_id) = ctx.paths.get_active_save() else {
+            return Ok(Snapshot(None));
+        };
+        let undo_dir = ctx.paths.get_save_undo_dir(save_id);
+        let path = undo_dir.join(PENDING_SNAPSHOT);
+
+        fs::create_dir_all(&undo_dir)?;
+
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+
+        sqlx::query("VACUUM INTO $1;")
+            .bind(path.to_string_lossy())
+            .execute(&**ctx.pool()?)
+            .await?;
+
+        Ok(Snapshot(Some((save_id, path))))
+    }
+
+    /// Rolls the save back to the most recent snapshot. Returns false if there is nothing left to
+    /// undo
+    pub async fn pop(ctx: &OakenCtx) -> OakenResult<bool> {
+        let Some(save_id) = ctx.paths.get_active_save() else {
+            return Ok(false);
+        };
+
+        let Some(latest) = Self::get_snapshots(ctx, save_id).pop() else {
+            return Ok(false);
+        };
+
+        let snapshot = ctx
+            .paths
+            .get_save_undo_dir(save_id)
+            .join(format!("{latest}.sqlite"));
+        let db_path = ctx.paths.get_save_db_path(save_id);
+
+        // anything reading the save from here until it's opened again gets a `NoSaveOpen` error,
+        // rather than a database half way through being replaced
+        ctx.close_pool().await;
+        let swapped = Self::swap_in(&snapshot, &db_path);
+
+        // the save is opened again even if the snapshot didn't make it in, so that a failed undo
+        // doesn't leave the game without one
+        ctx.init_pool(&db_path).await?;
+        swapped?;
+
+        Ok(true)
+    }
+
+    /// Puts `snapshot` in place of the database at `db_path`, which must be closed
+    fn swap_in(snapshot: &Path, db_path: &Path) -> OakenResult<()> {
+        // any journal left beside the database belongs to the state being thrown away
+        for suffix in ["-wal", "-shm"] {
+            let journal = db_path.with_file_name(format!("db.sqlite{suffix}"));
+
+            if journal.exists() {
+                fs::remove_file(journal)?;
+            }
+        }
+
+        fs::rename(snapshot, db_path)?;
+
+        Ok(())
+    }
+
+    /// Forgets every snapshot of the save in use, for when there is no going back
+    pub fn clear(ctx: &OakenCtx) -> OakenResult<()> {
+        let Some(save_id) = ctx.paths.get_active_save() else {
+            return Ok(());
+        };
+        let undo_dir = ctx.paths.get_save_undo_dir(save_id);
+
+        if undo_dir.exists() {
+            fs::remove_dir_all(undo_dir)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A copy of the save from just before a week was played, which only joins the history once the
+/// week has been played in full
+pub struct Snapshot(Option<(i32, PathBuf)>);
+
+impl Snapshot {
+    /// Adds the snapshot to the history, dropping the oldest once there are more than
+    /// [`UNDO_DEPTH`]
+    pub fn keep(self, ctx: &OakenCtx) -> OakenResult<()> {
+        let Some((save_id, path)) = self.0 else {
+            return Ok(());
+        };
+        let undo_dir = ctx.paths.get_save_undo_dir(save_id);
+        let snapshots = UndoHistory::get_snapshots(ctx, save_id);
+
+        let next = snapshots.last().map_or(0, |last| last + 1);
+        fs::rename(path, undo_dir.join(format!("{next}.sqlite")))?;
+
+        let excess = (snapshots.len() + 1).saturating_sub(UNDO_DEPTH);
+
+        for old in snapshots.into_iter().take(excess) {
+            let _ = fs::remove_file(undo_dir.join(format!("{old}.sqlite")));
+        }
+
+        Ok(())
+    }
+
+    /// Throws the snapshot away, for a week that didn't get played
+    pub fn discard(self) {
+        if let Some((_, path)) = self.0 {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
@@ -3,7 +3,7 @@ import { ReactNode } from "react";
 import { useEventHandler } from "./lib/events";
 
 /**
- * Invalidates ALL queries whenever a "game_did_restart" message is recieved
+ * Invalidates ALL queries whenever the game restarts, advances or rewinds, or switches to another save
  */
 export function Invalidator(props: { children: ReactNode }) {
   const client = useQueryClient();
@@ -16,5 +16,13 @@ export function Invalidator(props: { children: ReactNode }) {
     client.invalidateQueries();
   });
 
+  useEventHandler("week_did_undo", () => {
+    client.invalidateQueries();
+  });
+
+  useEventHandler("save_did_load", () => {
+    client.invalidateQueries();
+  });
+
   return props.children;
 }
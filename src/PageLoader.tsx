@@ -1,11 +1,10 @@
-import { useEventHandler } from "@/lib/events";
-import { EmitMsg } from "@/lib/msg_bindings";
+import { EmitKind, useEventHandler } from "@/lib/events";
 import { Loader2 } from "lucide-react";
 import { ReactNode, useState } from "react";
 
-const START_LOADING_ON_EVENTS = ["game_will_restart"] satisfies readonly EmitMsg[];
+const START_LOADING_ON_EVENTS = ["game_will_restart"] satisfies readonly EmitKind[];
 
-const STOP_LOADING_ON_EVENTS = ["game_did_restart"] satisfies readonly EmitMsg[];
+const STOP_LOADING_ON_EVENTS = ["game_did_restart"] satisfies readonly EmitKind[];
 
 export function PageLoader(props: { children: ReactNode }) {
   const [isLoading, setIsLoading] = useState(false);
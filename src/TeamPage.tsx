@@ -7,8 +7,7 @@ import { Button } from "./lib/ui/button";
 import { ArrowLeft, AtSign } from "lucide-react";
 import { Table, TableBody, TableCell, TableFooter, TableHead, TableHeader, TableRow } from "./lib/ui/table";
 import { maybe } from "@tsly/maybe";
-import { iter } from "@tsly/iter";
-import { Matchup, TeamInfo } from "./bindings";
+import { TeamInfo } from "./bindings";
 import { TeamSelect } from "@/lib/prefab/TeamSelect";
 import { NextWeekButton } from "@/lib/prefab/NextWeekButton";
 
@@ -22,8 +21,23 @@ function TeamPage() {
   });
 
   const { data: matchups } = useQuery({
-    queryKey: ["getTeamMatchups", id],
-    queryFn: () => api.query(["getTeamMatchups", parseInt(id)]),
+    queryKey: ["searchMatchups", id, game?.year],
+    enabled: !!game,
+    queryFn: async () => {
+      const page = await api.query(["searchMatchups", { teamId: parseInt(id), year: game?.year, limit: 500 }]);
+      return page.matchups;
+    },
+  });
+
+  const { data: season } = useQuery({
+    queryKey: ["getTeamSeason", id, game?.year],
+    enabled: !!game,
+    queryFn: () => api.query(["getTeamSeason", { teamId: parseInt(id), year: game!.year }]),
+  });
+
+  const { data: history } = useQuery({
+    queryKey: ["getTeamHistory", id],
+    queryFn: () => api.query(["getTeamHistory", parseInt(id)]),
   });
 
   const { data: teams } = useQuery({
@@ -47,11 +61,21 @@ function TeamPage() {
             <h1 className="text-4xl font-extrabold tracking-tight">{getTeamById(id)?.name}</h1>
 
             <div className="flex gap-2 items-center">
+              {maybe(getTeamById(id))?.take((team) => (
+                <span>
+                  <Badge style={{ backgroundColor: team.primary_color, color: team.secondary_color }}>{team.abbr}</Badge>
+                </span>
+              ))}
               <span>
                 <Badge>
                   {getTeamById(id)?.league.name} // {getTeamById(id)?.tier.name}
                 </Badge>
               </span>
+              <span>
+                <Badge variant={"secondary"}>
+                  {getTeamById(id)?.city}, est. {getTeamById(id)?.founded}
+                </Badge>
+              </span>
               <span>
                 <Badge variant={"secondary"}>Skill: {getTeamById(id)?.skill}</Badge>
               </span>
@@ -75,25 +99,22 @@ function TeamPage() {
             <TableHead>Away Team</TableHead>
             <TableHead>Home Team</TableHead>
             <TableHead>Result</TableHead>
+            <TableHead>Attendance</TableHead>
           </TableHeader>
           <TableBody>
             {matchups?.map((matchup) => (
               <TableRow>
                 <TableCell>{matchup.wkNo}</TableCell>
                 <TableCell>
-                  {maybe(getTeamById(matchup.awayTeamId))?.take((team) => (
-                    <Button variant={"link"} onClick={() => nav("/team/" + team.id)}>
-                      {team.name}
-                    </Button>
-                  ))}
+                  <Button variant={"link"} onClick={() => nav("/team/" + matchup.awayTeamId)}>
+                    {matchup.awayTeamName}
+                  </Button>
                 </TableCell>
                 <TableCell>
-                  {maybe(getTeamById(matchup.homeTeamId))?.take((team) => (
-                    <Button variant={"link"} onClick={() => nav("/team/" + team.id)}>
-                      <AtSign className="w-4 h-4 mr-2" />
-                      {team.name}
-                    </Button>
-                  ))}
+                  <Button variant={"link"} onClick={() => nav("/team/" + matchup.homeTeamId)}>
+                    <AtSign className="w-4 h-4 mr-2" />
+                    {matchup.homeTeamName}
+                  </Button>
                 </TableCell>
                 <TableCell>
                   {maybe(matchup.homeTeamScore)?.take((homeTeamScore) =>
@@ -108,33 +129,63 @@ function TeamPage() {
                     }),
                   ) ?? "-"}
                 </TableCell>
+                <TableCell>{matchup.attendance?.toLocaleString() ?? "-"}</TableCell>
               </TableRow>
             ))}
           </TableBody>
+          <TableFooter>
+            {maybe(season)?.take(({ standing, home, away }) =>
+              (
+                [
+                  ["Win/Draw/Loss", standing],
+                  ["Home", home],
+                  ["Away", away],
+                ] as const
+              ).map(([label, { wins, draws, losses }]) => (
+                <TableRow>
+                  <TableCell colSpan={3}>{label}</TableCell>
+                  <TableCell className="text-center">{`${wins}/${draws}/${losses}`}</TableCell>
+                  <TableCell />
+                </TableRow>
+              )),
+            )}
+          </TableFooter>
+        </Table>
+      </div>
+
+      <h3 className="text-2xl text-gray-700 font-bold my-4 ml-8">History</h3>
+
+      <div className="w-[calc(100%-4rem)] mx-8 border-px rounded-md shadow-lg">
+        <Table>
+          <TableHeader>
+            <TableHead>Year</TableHead>
+            <TableHead>Tier</TableHead>
+            <TableHead>Finish</TableHead>
+            <TableHead>Points</TableHead>
+            <TableHead>Honours</TableHead>
+          </TableHeader>
+          <TableBody>
+            {history?.seasons
+              .slice()
+              .reverse()
+              .map((each) => (
+                <TableRow>
+                  <TableCell>{each.year}</TableCell>
+                  <TableCell>{each.tier.name}</TableCell>
+                  <TableCell>{each.finished ? `${each.rank}.` : `${each.rank}. (so far)`}</TableCell>
+                  <TableCell>{each.points}</TableCell>
+                  <TableCell className="flex gap-2">
+                    {each.champion && <Badge>Champions</Badge>}
+                    {each.tierMove == "promoted" && <Badge variant={"secondary"}>Promoted</Badge>}
+                    {each.tierMove == "relegated" && <Badge variant={"destructive"}>Relegated</Badge>}
+                  </TableCell>
+                </TableRow>
+              ))}
+          </TableBody>
           <TableFooter>
             <TableRow>
-              <TableCell colSpan={3}>Win/Loss</TableCell>
-              <TableCell className="text-center">
-                {iter(matchups ?? [])
-                  .filterMap((match) => {
-                    return (
-                      maybe(match.homeTeamScore)?.take((homeTeamScore) =>
-                        maybe(match.awayTeamScore)?.take((awayTeamScore) => {
-                          if (homeTeamScore == awayTeamScore) return null; // skip
-                          return [match, homeTeamScore, awayTeamScore] as readonly [match: Matchup, homeScore: number, awayScore: number];
-                        }),
-                      ) ?? null
-                    );
-                  })
-                  .partition(([match, homeTeamScore, awayTeamScore]) => {
-                    if (homeTeamScore > awayTeamScore && match.homeTeamId == parseInt(id)) return true; // win
-                    if (homeTeamScore < awayTeamScore && match.awayTeamId == parseInt(id)) return true; // win
-
-                    return false; // loss
-                  })
-                  .map((each) => each.count().toString())
-                  .join("/")}
-              </TableCell>
+              <TableCell colSpan={4}>Titles/Promotions/Relegations</TableCell>
+              <TableCell>{maybe(history)?.take(({ titles, promotions, relegations }) => `${titles}/${promotions}/${relegations}`)}</TableCell>
             </TableRow>
           </TableFooter>
         </Table>
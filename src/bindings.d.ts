@@ -2,31 +2,76 @@
 
 export type Procedures = {
     queries: 
-        { key: "getAllSchedules", input: never, result: Schedule[] } | 
+        { key: "getCareer", input: never, result: Career } | 
+        { key: "getCoach", input: number, result: Coach | null } | 
+        { key: "getCoachingHistory", input: number, result: CoachingSpell[] } | 
+        { key: "getFinances", input: GetFinancesArgs, result: Finances | null } | 
         { key: "getGameState", input: never, result: GameState } | 
+        { key: "getHeadToHead", input: GetHeadToHeadArgs, result: HeadToHead } | 
         { key: "getLeagueInfos", input: never, result: LeagueInfo[] } | 
-        { key: "getMatchupsByWeek", input: GetMatchupsByWeekArgs, result: Matchup[] } | 
+        { key: "getManagerInbox", input: never, result: InboxMessage[] } | 
+        { key: "getRecords", input: RecordScope, result: Record[] } | 
         { key: "getSchedulesByYear", input: number, result: Schedule[] } | 
+        { key: "getSeasonChampions", input: number, result: SeasonChampion[] } | 
+        { key: "getSeasons", input: never, result: Season[] } | 
         { key: "getStandings", input: number, result: Standing[] } | 
+        { key: "getTeamHistory", input: number, result: TeamHistory } | 
         { key: "getTeamInfos", input: never, result: TeamInfo[] } | 
-        { key: "getTeamMatchups", input: number, result: Matchup[] } | 
-        { key: "getTeams", input: never, result: Team[] },
+        { key: "getTeamSeason", input: GetTeamSeasonArgs, result: TeamSeason } | 
+        { key: "getTeams", input: never, result: Team[] } | 
+        { key: "listSaves", input: never, result: SaveSlot[] } | 
+        { key: "searchMatchups", input: MatchupSearch, result: MatchupPage },
     mutations: 
-        { key: "advanceWeek", input: never, result: null },
-    subscriptions: never
+        { key: "advanceWeek", input: never, result: null } | 
+        { key: "createSave", input: string, result: SaveSlot } | 
+        { key: "deleteSave", input: number, result: SaveSlot[] } | 
+        { key: "exportSave", input: string, result: null } | 
+        { key: "importSave", input: string, result: SaveSlot } | 
+        { key: "loadSave", input: number, result: SaveSlot } | 
+        { key: "rebuildStandings", input: number, result: number } | 
+        { key: "renameSave", input: RenameSaveArgs, result: SaveSlot } | 
+        { key: "setManagedTeam", input: number, result: Career } | 
+        { key: "setManagerPlan", input: ManagerPlan, result: Career } | 
+        { key: "undoWeek", input: never, result: boolean },
+    subscriptions: 
+        { key: "gameEvents", input: never, result: EmitMsg }
 };
 
 export type LeagueInfo = { id: number; name: string; abbr: string; tiers: Tier[] }
 
-export type Team = { id: number; name: string; skill: number; tier_id: number }
+export type Team = { id: number; name: string; abbr: string; city: string; founded: number; primary_color: string; secondary_color: string; skill: number; capacity: number; tier_id: number }
 
-export type Standing = { teamId: number; leagueId: number; tierId: number; teamName: string; wins: number; losses: number; draws: number; pointsFor: number; pointsAgainst: number; streak: number; winPercent: number | null }
+export type Standing = { teamId: number; leagueId: number; tierId: number; teamName: string; wins: number; losses: number; draws: number; pointsFor: number; pointsAgainst: number; streak: number; points: number; rank: number; winPercent: number | null }
 
-export type Matchup = { id: number; wkNo: number; homeTeamId: number; awayTeamId: number; homeTeamScore: number | null; awayTeamScore: number | null }
+export type Matchup = { id: number; wkNo: number; seasonId: number; homeTeamId: number; awayTeamId: number; homeTeamScore: number | null; awayTeamScore: number | null; attendance: number | null }
 
 export type League = { id: number; name: string; abbr: string }
 
-export type GetMatchupsByWeekArgs = { year: number; wkNo: number }
+export type Career = { managedTeamId: number | null; tactic: Tactic; training: Training; squadSpend: number; jobSecurity: number }
+
+export type Tactic = "attacking" | "balanced" | "defensive"
+
+export type Training = "sharpness" | "development"
+
+export type ManagerPlan = { tactic: Tactic; training: Training; squadSpend: number }
+
+export type InboxMessage = { id: number; year: number; wkNo: number; subject: string; body: string }
+
+export type Coach = { id: number; name: string; rating: number; teamId: number | null }
+
+export type CoachingSpell = { coachId: number; coachName: string; rating: number; teamId: number; startYear: number; startWk: number; endYear: number | null; endWk: number | null }
+
+export type SaveSlot = { id: number; name: string; createdAt: string | null; year: number; wkNo: number; managedTeam: string | null; active: boolean }
+
+export type RenameSaveArgs = { saveId: number; name: string }
+
+export type GetHeadToHeadArgs = { teamAId: number; teamBId: number }
+
+export type GetTeamSeasonArgs = { teamId: number; year: number }
+
+export type GetFinancesArgs = { teamId: number; year: number }
+
+export type Finances = { teamId: number; year: number; openingBalance: number; budget: number; gateRevenue: number; prizeMoney: number; tierChange: number; wages: number; balance: number }
 
 export type Tier = { id: number; name: string; rank: number; leagueId: number }
 
@@ -34,4 +79,44 @@ export type GameState = { schema_ver: number; year: number; wk_no: number }
 
 export type Schedule = { id: number; year: number; tier_id: number; league_id: number; matchups: Matchup[] }
 
-export type TeamInfo = { id: number; name: string; skill: number; tier: Tier; league: League }
+export type TeamInfo = { id: number; name: string; abbr: string; city: string; founded: number; primary_color: string; secondary_color: string; skill: number; capacity: number; tier: Tier; league: League }
+
+export type SeasonPhase = "preseason" | "regular" | "playoffs" | "offseason"
+
+export type Season = { id: number; year: number; phase: SeasonPhase; startWk: number; endWk: number }
+
+export type SeasonChampion = { seasonId: number; leagueId: number; tierId: number; teamId: number }
+
+export type EmitMsg = { kind: "game_will_restart" } | { kind: "game_did_restart"; year: number } | { kind: "week_did_advance"; year: number; wkNo: number; results: Matchup[] } | { kind: "week_did_undo"; year: number; wkNo: number } | { kind: "records_were_broken"; records: Record[] } | { kind: "save_did_load"; saveId: number }
+
+export type Outcome = "win" | "draw" | "loss"
+
+export type MatchupSort = "date" | "attendance" | "margin" | "totalScore"
+
+export type MatchupSearch = { year?: number | null; fromWk?: number | null; toWk?: number | null; leagueId?: number | null; tierId?: number | null; teamId?: number | null; opponentId?: number | null; played?: boolean | null; result?: Outcome | null; sort?: MatchupSort; descending?: boolean; cursor?: string | null; limit?: number | null }
+
+export type MatchupDetail = ({ id: number; wkNo: number; seasonId: number; homeTeamId: number; awayTeamId: number; homeTeamScore: number | null; awayTeamScore: number | null; attendance: number | null }) & { year: number; leagueId: number; tierId: number; homeTeamName: string; awayTeamName: string }
+
+export type MatchupPage = { matchups: MatchupDetail[]; nextCursor: string | null }
+
+export type HeadToHeadStreak = { outcome: Outcome; len: number }
+
+export type HeadToHead = { teamAId: number; teamBId: number; meetings: Matchup[]; teamAWins: number; draws: number; teamBWins: number; teamAPoints: number; teamBPoints: number; teamABiggestWin: Matchup | null; teamBBiggestWin: Matchup | null; streak: HeadToHeadStreak | null }
+
+export type TierMove = "promoted" | "stayed" | "relegated"
+
+export type TeamSeasonSummary = { year: number; tier: Tier; rank: number; points: number; finished: boolean; champion: boolean; tierMove: TierMove | null }
+
+export type TeamHistory = { teamId: number; seasons: TeamSeasonSummary[]; titles: number; promotions: number; relegations: number }
+
+export type TeamWeek = { wkNo: number; matchupId: number | null; outcome: Outcome | null; points: number; rank: number }
+
+export type VenueSplit = { wins: number; draws: number; losses: number; pointsFor: number; pointsAgainst: number }
+
+export type TeamSeason = { teamId: number; year: number; tier: Tier; standing: Standing; finished: boolean; champion: boolean; tierMove: TierMove | null; rankChange: number; weeks: TeamWeek[]; bestResult: Matchup | null; worstResult: Matchup | null; home: VenueSplit; away: VenueSplit }
+
+export type RecordKind = "biggest_win" | "highest_scoring_match" | "longest_win_streak" | "longest_unbeaten_run" | "longest_losing_streak" | "most_season_points" | "most_titles"
+
+export type RecordScope = { kind: "world" } | { kind: "league"; id: number } | { kind: "team"; id: number }
+
+export type Record = { kind: RecordKind; scope: RecordScope; value: number; teamId: number | null; matchupId: number | null; year: number; wkNo: number }
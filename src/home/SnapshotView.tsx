@@ -9,8 +9,8 @@ import { useNavigate } from "react-router";
 import { useGlobalState } from "@/lib/utils";
 
 export function SnapshotView() {
-  const [leagueId] = useGlobalState("leagueId");
-  const [tierId] = useGlobalState("tierId");
+  const [leagueId] = useGlobalState<number>("leagueId");
+  const [tierId] = useGlobalState<number>("tierId");
 
   const { data: game } = useQuery({
     queryKey: ["getGameState"],
@@ -18,28 +18,18 @@ export function SnapshotView() {
   });
 
   const { data: matchups } = useQuery({
-    queryKey: ["getMatchupsByWeek", game?.wk_no, leagueId, tierId],
+    queryKey: ["searchMatchups", game?.year, game?.wk_no, leagueId, tierId],
     enabled: !!game && !!leagueId && !!tierId,
     queryFn: async () => {
-      const schedules = await api.query(["getSchedulesByYear", game?.year!]);
-      const matchups = schedules.filter((it) => it.league_id == leagueId && it.tier_id == tierId).flatMap((each) => each.matchups);
+      const page = await api.query([
+        "searchMatchups",
+        { year: game?.year, fromWk: Math.max(game!.wk_no - 1, 1), toWk: game?.wk_no, leagueId, tierId, descending: true, limit: 500 },
+      ]);
 
-      const curWkMatchups = matchups.filter((it) => it.wkNo == game?.wk_no);
-      const prevWkMatchups =
-        maybe(game?.wk_no)
-          ?.if((it) => it > 1)
-          ?.let((it) => it - 1)
-          .take((wkNo) => matchups.filter((it) => it.wkNo == wkNo)) ?? [];
-
-      return curWkMatchups.concat(prevWkMatchups);
+      return page.matchups;
     },
   });
 
-  const { data: teams } = useQuery({
-    queryKey: ["getTeams"],
-    queryFn: () => api.query(["getTeams"]),
-  });
-
   const nav = useNavigate();
 
   return leagueId == undefined || tierId == undefined ? (
@@ -63,19 +53,15 @@ export function SnapshotView() {
               <TableRow className="hover:bg-inherit">
                 <TableCell>{matchup.wkNo}</TableCell>
                 <TableCell className="p-0">
-                  {maybe(teams?.find((team) => team.id == matchup.awayTeamId))?.take((team) => (
-                    <Button variant={"link"} onClick={() => nav("/team/" + team.id)}>
-                      {team.name}
-                    </Button>
-                  ))}
+                  <Button variant={"link"} onClick={() => nav("/team/" + matchup.awayTeamId)}>
+                    {matchup.awayTeamName}
+                  </Button>
                 </TableCell>
                 <TableCell className="p-0">
-                  {maybe(teams?.find((team) => team.id == matchup.homeTeamId))?.take((team) => (
-                    <Button variant={"link"} onClick={() => nav("/team/" + team.id)}>
-                      <AtSign className="h-4 w-4 mr-2" />
-                      {team.name}
-                    </Button>
-                  ))}
+                  <Button variant={"link"} onClick={() => nav("/team/" + matchup.homeTeamId)}>
+                    <AtSign className="h-4 w-4 mr-2" />
+                    {matchup.homeTeamName}
+                  </Button>
                 </TableCell>
                 <TableCell>
                   {maybe(matchup.awayTeamScore)?.take((awayTeamScore) =>
@@ -56,11 +56,14 @@ export function StandingsView() {
             <TableHead>
               <abbr title="Streak">Strk</abbr>
             </TableHead>
+            <TableHead>
+              <abbr title="Points">Pts</abbr>
+            </TableHead>
           </TableHeader>
           <TableBody>
-            {standings?.map((each, i) => (
+            {standings?.map((each) => (
               <TableRow className="hover:bg-inherit">
-                <TableCell>{i + 1}.</TableCell>
+                <TableCell>{each.rank}.</TableCell>
                 <TableCell className="p-0">
                   <Button variant={"link"} onClick={() => nav(`/team/${each.teamId}`)}>
                     {each.teamName}
@@ -73,6 +76,7 @@ export function StandingsView() {
                 <TableCell>{each.pointsFor}</TableCell>
                 <TableCell>{each.pointsAgainst}</TableCell>
                 <TableCell>{each.streak}</TableCell>
+                <TableCell>{each.points}</TableCell>
               </TableRow>
             ))}
           </TableBody>
@@ -1,15 +1,17 @@
-import { listen } from "@tauri-apps/api/event";
 import { useEffect } from "react";
-import { EmitMsg } from "@/lib/msg_bindings";
+import { EmitMsg } from "@/bindings";
+import { api } from "@/lib/rpc";
 
-export function useEventHandler(evtId: EmitMsg, handler: () => void) {
+export type EmitKind = EmitMsg["kind"];
+
+export function useEventHandler<K extends EmitKind>(kind: K, handler: (msg: Extract<EmitMsg, { kind: K }>) => void) {
   useEffect(() => {
-    const unlisten = listen(evtId, () => {
-      handler();
+    const unsubscribe = api.addSubscription(["gameEvents"], {
+      onData: (msg) => {
+        if (msg.kind == kind) handler(msg as Extract<EmitMsg, { kind: K }>);
+      },
     });
 
-    return () => {
-      unlisten.then((f) => f());
-    };
+    return () => unsubscribe();
   }, []);
 }
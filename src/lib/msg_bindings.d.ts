@@ -1,3 +0,0 @@
-// This file was generated by Oaken. Do not edit this file manually!
-
-export type EmitMsg = "game_will_restart" | "game_did_restart" | "week_did_advance"
\ No newline at end of file